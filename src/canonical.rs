@@ -0,0 +1,58 @@
+//! Deterministic JSON encoding for hashing and signing.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Serialize `value` as compact JSON with every object's keys sorted
+/// recursively, so structurally-equal values always produce identical bytes.
+pub fn to_canonical_json<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&canonicalize(serde_json::to_value(value)?))
+}
+
+/// Rebuild objects with sorted keys. `serde_json::Map` is already sorted by
+/// default, but not when a downstream crate enables `preserve_order`.
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, canonicalize(v)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(canonicalize).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn differently_ordered_maps_produce_identical_json() {
+        let a: HashMap<&str, Value> = [
+            ("zeta", serde_json::json!({"b": 2, "a": 1})),
+            ("alpha", serde_json::json!([{"y": true, "x": false}])),
+        ]
+        .into_iter()
+        .collect();
+        let b: HashMap<&str, Value> = [
+            ("alpha", serde_json::json!([{"x": false, "y": true}])),
+            ("zeta", serde_json::json!({"a": 1, "b": 2})),
+        ]
+        .into_iter()
+        .collect();
+
+        let canonical = to_canonical_json(&a).unwrap();
+        assert_eq!(canonical, to_canonical_json(&b).unwrap());
+        assert_eq!(
+            canonical,
+            r#"{"alpha":[{"x":false,"y":true}],"zeta":{"a":1,"b":2}}"#
+        );
+    }
+}
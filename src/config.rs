@@ -1,7 +1,11 @@
+use crate::messages::AgentRegister;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct GatewayConfig {
     pub server: ServerConfig,
     pub providers: Vec<ProviderConfig>,
@@ -16,14 +20,50 @@ pub struct GatewayConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ServerConfig {
     pub host: String,
+    /// Accepts an integer or a numeric string (e.g. `"8080"` from env
+    /// templating); always serialized as an integer.
+    #[serde(deserialize_with = "port_from_int_or_string")]
     pub port: u16,
 }
 
+fn port_from_int_or_string<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u16, D::Error> {
+    struct PortVisitor;
+
+    impl serde::de::Visitor<'_> for PortVisitor {
+        type Value = u16;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("a port number or a numeric string")
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<u16, E> {
+            u16::try_from(v).map_err(|_| E::custom(format!("port {v} is out of range")))
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<u16, E> {
+            u16::try_from(v).map_err(|_| E::custom(format!("port {v} is out of range")))
+        }
+
+        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<u16, E> {
+            v.trim()
+                .parse()
+                .map_err(|_| E::custom(format!("invalid port `{v}`")))
+        }
+    }
+
+    deserializer.deserialize_any(PortVisitor)
+}
+
 /// Which wire protocol the provider speaks.
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum ProviderType {
     /// OpenAI-compatible REST API (OpenAI, OpenRouter, Ollama, vLLM, etc.)
     #[default]
@@ -42,9 +82,97 @@ pub enum ProviderType {
     Google,
     /// GitHub Copilot — token exchange flow + OpenAI-compatible wire format.
     GithubCopilot,
+    /// Out-of-tree provider registered at runtime under this name
+    /// (see [`crate::providers::ProviderRegistry`]).
+    Custom(String),
 }
 
+impl ProviderType {
+    /// The serialized (`snake_case`) name of a built-in provider type. For
+    /// `Custom` it is the bare registered name, whereas serde writes
+    /// `{"custom": name}`, so it can coincide with a built-in's name.
+    pub fn as_str(&self) -> &str {
+        match self {
+            ProviderType::OpenAiCompatible => "open_ai_compatible",
+            ProviderType::Anthropic => "anthropic",
+            ProviderType::Cursor => "cursor",
+            ProviderType::ClaudeCode => "claude_code",
+            ProviderType::CodexCli => "codex_cli",
+            ProviderType::CodexAuth => "codex_auth",
+            ProviderType::Google => "google",
+            ProviderType::GithubCopilot => "github_copilot",
+            ProviderType::Custom(name) => name,
+        }
+    }
+
+    /// Human-readable label for UIs and logs, e.g. `"OpenAI-compatible"`;
+    /// the registered name for `Custom`. `Display` prints this for built-in
+    /// types and `custom:<name>` for `Custom`, so the two can't be confused.
+    pub fn display_name(&self) -> &str {
+        match self {
+            ProviderType::OpenAiCompatible => "OpenAI-compatible",
+            ProviderType::Anthropic => "Anthropic",
+            ProviderType::Cursor => "Cursor",
+            ProviderType::ClaudeCode => "Claude Code",
+            ProviderType::CodexCli => "Codex CLI",
+            ProviderType::CodexAuth => "Codex (OAuth)",
+            ProviderType::Google => "Google Gemini",
+            ProviderType::GithubCopilot => "GitHub Copilot",
+            ProviderType::Custom(name) => name,
+        }
+    }
+
+    /// HTTP header carrying the API key; `None` for CLI providers and
+    /// `Custom` providers, whose convention isn't known here.
+    pub fn auth_header_name(&self) -> Option<&'static str> {
+        match self {
+            ProviderType::OpenAiCompatible
+            | ProviderType::CodexAuth
+            | ProviderType::GithubCopilot => Some("Authorization"),
+            ProviderType::Anthropic => Some("x-api-key"),
+            ProviderType::Google => Some("x-goog-api-key"),
+            ProviderType::Cursor
+            | ProviderType::ClaudeCode
+            | ProviderType::CodexCli
+            | ProviderType::Custom(_) => None,
+        }
+    }
+
+    /// `(header, value)` authenticating a request with `key`, e.g.
+    /// `("Authorization", "Bearer sk-...")` for OpenAI-compatible providers.
+    pub fn format_auth(&self, key: &str) -> Option<(String, String)> {
+        let name = self.auth_header_name()?;
+        let value = if name == "Authorization" {
+            format!("Bearer {key}")
+        } else {
+            key.to_string()
+        };
+        Some((name.to_string(), value))
+    }
+
+    /// `true` for providers that spawn a local CLI instead of speaking HTTP.
+    pub fn is_cli(&self) -> bool {
+        matches!(
+            self,
+            ProviderType::Cursor | ProviderType::ClaudeCode | ProviderType::CodexCli
+        )
+    }
+}
+
+impl std::fmt::Display for ProviderType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProviderType::Custom(name) => write!(f, "custom:{name}"),
+            other => f.write_str(other.display_name()),
+        }
+    }
+}
+
+/// `anthropic-version` sent with every Anthropic Messages API request.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ProviderConfig {
     pub name: String,
     pub base_url: String,
@@ -54,13 +182,14 @@ pub struct ProviderConfig {
     #[serde(default)]
     pub api_key_envs: Vec<String>,
     pub enabled: bool,
-    /// Wire protocol this provider uses.
+    /// Wire protocol this provider uses. Defaults to `open_ai_compatible`
+    /// only when absent; an unrecognized name fails to parse.
     #[serde(default)]
     pub provider_type: ProviderType,
     /// Optional extra HTTP headers sent on every request (e.g. OpenRouter's
     /// `HTTP-Referer` and `X-Title`).
     #[serde(default)]
-    pub extra_headers: HashMap<String, String>,
+    pub extra_headers: BTreeMap<String, String>,
     #[serde(default)]
     pub rate_limit: Option<RateLimitConfig>,
     /// Known model IDs this provider supports.
@@ -73,10 +202,117 @@ pub struct ProviderConfig {
     /// When present, `/v1/models` responses include context_window, max_tokens, etc.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub model_metadata: Option<HashMap<String, ModelMetadata>>,
+    /// Optional endpoint the gateway probes before routing to this provider.
+    /// Ignored for CLI providers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<ProviderHealthCheck>,
+}
+
+/// How to probe a provider's health over HTTP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProviderHealthCheck {
+    /// Path relative to `base_url`, e.g. `/models`.
+    pub path: String,
+    /// HTTP status a healthy provider returns (default: 200).
+    #[serde(default = "default_health_expected_status")]
+    pub expected_status: u16,
+    /// Seconds between probes (default: 30).
+    #[serde(default = "default_health_interval_secs")]
+    pub interval_secs: u64,
+}
+
+fn default_health_expected_status() -> u16 {
+    200
+}
+fn default_health_interval_secs() -> u64 {
+    30
+}
+
+impl ProviderConfig {
+    /// Full URL to probe, joining `base_url` and the health-check `path` with
+    /// exactly one `/`. `None` for CLI providers or when no check is configured.
+    pub fn health_url(&self) -> Option<String> {
+        if self.provider_type.is_cli() {
+            return None;
+        }
+        let check = self.health_check.as_ref()?;
+        Some(format!(
+            "{}/{}",
+            self.base_url.trim_end_matches('/'),
+            check.path.trim_start_matches('/')
+        ))
+    }
+
+    /// One-line description safe to log: key env names are reduced to a
+    /// count and extra headers are left out.
+    pub fn masked_summary(&self) -> String {
+        format!(
+            "{} [{}] {} keys={} {}",
+            self.name,
+            self.provider_type.as_str(),
+            self.base_url,
+            self.api_key_envs.len(),
+            if self.enabled { "enabled" } else { "disabled" }
+        )
+    }
+
+    /// Headers for an upstream request, names lowercased: protocol constants
+    /// (e.g. Anthropic's `anthropic-version`), then `extra_headers`, then the
+    /// auth header for `resolved_key`. A later header replaces an earlier one
+    /// with the same name regardless of casing, so `extra_headers` can
+    /// override a constant but not the auth header. Empty for CLI providers.
+    pub fn request_headers(&self, resolved_key: Option<&str>) -> Vec<(String, String)> {
+        if self.provider_type.is_cli() {
+            return Vec::new();
+        }
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut set = |name: &str, value: &str| {
+            let name = name.to_ascii_lowercase();
+            match headers.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, v)) => *v = value.to_string(),
+                None => headers.push((name, value.to_string())),
+            }
+        };
+        if self.provider_type == ProviderType::Anthropic {
+            set("anthropic-version", ANTHROPIC_VERSION);
+        }
+        for (name, value) in &self.extra_headers {
+            set(name, value);
+        }
+        if let Some((name, value)) =
+            resolved_key.and_then(|key| self.provider_type.format_auth(key))
+        {
+            set(&name, &value);
+        }
+        headers
+    }
+
+    /// Values of the `api_key_envs` variables, in order.
+    pub fn resolve_api_keys(&self) -> Result<Vec<String>, ConfigError> {
+        self.api_key_envs
+            .iter()
+            .map(|var| {
+                std::env::var(var).map_err(|_| ConfigError::MissingEnv {
+                    provider: self.name.clone(),
+                    var: var.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// `base_url` with each `${VAR}` replaced by that variable's value.
+    pub fn expanded_base_url(&self) -> Result<String, ConfigError> {
+        expand_env_vars(&self.base_url).map_err(|var| ConfigError::MissingEnv {
+            provider: self.name.clone(),
+            var,
+        })
+    }
 }
 
 /// Rich metadata for a single model — context window, pricing, capabilities.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ModelMetadata {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub context_window: Option<u32>,
@@ -92,6 +328,7 @@ pub struct ModelMetadata {
 
 /// Per-model pricing in USD per 1M tokens.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ModelCost {
     pub input: f64,
     pub output: f64,
@@ -102,13 +339,23 @@ pub struct ModelCost {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RateLimitConfig {
     pub requests_per_minute: u32,
     pub burst_size: u32,
 }
 
+impl RateLimitConfig {
+    /// Sustained rate as tokens added per second, for token-bucket limiters
+    /// such as [`RateLimiter`](crate::providers::RateLimiter).
+    pub fn refill_per_second(&self) -> f64 {
+        f64::from(self.requests_per_minute) / 60.0
+    }
+}
+
 /// Retry and fallback configuration for upstream provider requests.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ReliabilityConfig {
     /// Maximum retry attempts per provider before falling back (default: 3).
     #[serde(default = "default_max_retries")]
@@ -137,6 +384,7 @@ fn default_max_backoff_ms() -> u64 {
 
 /// Hint-based model routing configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RoutingConfig {
     /// Maps hint names to `provider:model` strings.
     /// Example: `{"coding": "anthropic:claude-opus-4-5", "fast": "openai:gpt-4o-mini"}`
@@ -155,10 +403,12 @@ pub struct AgentConfig {
 }
 
 impl GatewayConfig {
+    #[cfg(feature = "toml")]
     pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
         toml::from_str(content)
     }
 
+    #[cfg(feature = "toml")]
     pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
         toml::to_string_pretty(self)
     }
@@ -170,19 +420,425 @@ impl GatewayConfig {
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
     }
+
+    /// [`ProviderConfig::masked_summary`] of every provider, one per line.
+    pub fn summary(&self) -> String {
+        self.providers
+            .iter()
+            .map(ProviderConfig::masked_summary)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// What changed going from `self` to `other`, matching providers by
+    /// `name`. Lists keep the order providers appear in their config.
+    pub fn diff(&self, other: &GatewayConfig) -> ConfigDiff {
+        let as_json = |p: &ProviderConfig| serde_json::to_value(p).ok();
+        let before: HashMap<&str, &ProviderConfig> = self
+            .providers
+            .iter()
+            .map(|p| (p.name.as_str(), p))
+            .collect();
+        let after: HashSet<&str> = other.providers.iter().map(|p| p.name.as_str()).collect();
+        let mut diff = ConfigDiff {
+            server_changed: self.server.host != other.server.host
+                || self.server.port != other.server.port,
+            removed: self
+                .providers
+                .iter()
+                .filter(|p| !after.contains(p.name.as_str()))
+                .map(|p| p.name.clone())
+                .collect(),
+            ..ConfigDiff::default()
+        };
+        for provider in &other.providers {
+            match before.get(provider.name.as_str()) {
+                None => diff.added.push(provider.name.clone()),
+                Some(old) if as_json(old) != as_json(provider) => {
+                    diff.modified.push(provider.name.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff
+    }
+
+    /// Check the config is internally consistent: provider names are
+    /// non-empty and unique, HTTP providers have a `base_url`, enabled CLI
+    /// providers declare `models`, and every `fallback_chain` entry names a
+    /// configured provider.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut names = HashSet::new();
+        for provider in &self.providers {
+            if provider.name.is_empty() {
+                return Err(ConfigError::invalid("providers.name", "must not be empty"));
+            }
+            if !names.insert(provider.name.as_str()) {
+                return Err(ConfigError::invalid(
+                    "providers.name",
+                    format!("duplicate provider `{}`", provider.name),
+                ));
+            }
+            if !provider.provider_type.is_cli() && provider.base_url.is_empty() {
+                return Err(ConfigError::invalid(
+                    "providers.base_url",
+                    format!("provider `{}` has no base_url", provider.name),
+                ));
+            }
+            if provider.enabled && provider.provider_type.is_cli() && provider.models.is_empty() {
+                return Err(ConfigError::invalid(
+                    "providers.models",
+                    format!(
+                        "CLI provider `{}` must list its models; CLIs have no listing API",
+                        provider.name
+                    ),
+                ));
+            }
+        }
+        if let Some(reliability) = &self.reliability
+            && let Some(unknown) = reliability
+                .fallback_chain
+                .iter()
+                .find(|name| !names.contains(name.as_str()))
+        {
+            return Err(ConfigError::invalid(
+                "reliability.fallback_chain",
+                format!("unknown provider `{unknown}`"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Override parsed values from the environment, for deployments that keep
+    /// the config file in git and adjust it per host. Supported variables:
+    ///
+    /// - `EVO_GATEWAY_HOST` → `server.host`
+    /// - `EVO_GATEWAY_PORT` → `server.port` (ignored with a warning unless a
+    ///   valid port number)
+    /// - `EVO_PROVIDER_<NAME>_BASE_URL` → that provider's `base_url`, where
+    ///   `<NAME>` is the provider name uppercased with every character other
+    ///   than letters and digits replaced by `_` (`open-router` →
+    ///   `EVO_PROVIDER_OPEN_ROUTER_BASE_URL`)
+    ///
+    /// Unset variables leave the config untouched. Call before
+    /// [`validate`](Self::validate) so overrides are checked too.
+    pub fn apply_env_overrides(&mut self) {
+        let var = |name: &str| std::env::var(name).ok();
+        if let Some(host) = var("EVO_GATEWAY_HOST") {
+            self.server.host = host;
+        }
+        if let Some(port) = var("EVO_GATEWAY_PORT") {
+            match port.trim().parse() {
+                Ok(port) => self.server.port = port,
+                Err(_) => tracing::warn!(value = %port, "ignoring invalid EVO_GATEWAY_PORT"),
+            }
+        }
+        for provider in &mut self.providers {
+            if let Some(base_url) = var(&provider_env_var(&provider.name, "BASE_URL")) {
+                provider.base_url = base_url;
+            }
+        }
+    }
+
+    /// Validate, then resolve every enabled provider's API keys and expand
+    /// `${VAR}` references in its `base_url`, so nothing reads the
+    /// environment at request time. Disabled providers are dropped.
+    pub fn effective(self) -> Result<EffectiveGatewayConfig, ConfigError> {
+        self.validate()?;
+        let providers = self
+            .providers
+            .into_iter()
+            .filter(|provider| provider.enabled)
+            .map(|mut provider| {
+                let api_keys = provider.resolve_api_keys()?;
+                provider.base_url = provider.expanded_base_url()?;
+                Ok(EffectiveProvider {
+                    config: provider,
+                    api_keys,
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+        Ok(EffectiveGatewayConfig {
+            server: self.server,
+            providers,
+            reliability: self.reliability,
+            routing: self.routing,
+        })
+    }
+}
+
+/// `EVO_PROVIDER_<NAME>_<suffix>` for provider `name`; see
+/// [`GatewayConfig::apply_env_overrides`].
+fn provider_env_var(name: &str, suffix: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("EVO_PROVIDER_{name}_{suffix}")
+}
+
+/// Replace `${VAR}` references; on failure returns the name of the first
+/// unset variable. Text without a closing `}` is kept as is.
+fn expand_env_vars(value: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        let var = &rest[start + 2..start + len];
+        expanded.push_str(&std::env::var(var).map_err(|_| var.to_string())?);
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
 }
 
+/// A validated [`GatewayConfig`] with secrets and URLs resolved, produced by
+/// [`GatewayConfig::effective`].
+#[derive(Debug, Clone)]
+pub struct EffectiveGatewayConfig {
+    pub server: ServerConfig,
+    /// Enabled providers only.
+    pub providers: Vec<EffectiveProvider>,
+    pub reliability: Option<ReliabilityConfig>,
+    pub routing: Option<RoutingConfig>,
+}
+
+/// A provider with its API keys read from the environment and its
+/// `base_url` expanded.
+#[derive(Clone)]
+pub struct EffectiveProvider {
+    pub config: ProviderConfig,
+    /// Key values in `api_key_envs` order.
+    pub api_keys: Vec<String>,
+}
+
+impl std::fmt::Debug for EffectiveProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EffectiveProvider")
+            .field("config", &self.config)
+            .field(
+                "api_keys",
+                &format_args!("[{} redacted]", self.api_keys.len()),
+            )
+            .finish()
+    }
+}
+
+/// Why a config couldn't be validated or resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `provider` references environment variable `var`, which isn't set.
+    MissingEnv { provider: String, var: String },
+    /// `field` has an unusable value.
+    Invalid { field: &'static str, reason: String },
+    /// None of the `searched` config locations exist.
+    NotFound { searched: Vec<PathBuf> },
+    /// The config file at `path` exists but couldn't be read or parsed.
+    Load { path: PathBuf, reason: String },
+}
+
+impl ConfigError {
+    fn invalid(field: &'static str, reason: impl Into<String>) -> Self {
+        ConfigError::Invalid {
+            field,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::MissingEnv { provider, var } => {
+                write!(
+                    f,
+                    "provider `{provider}`: environment variable `{var}` is not set"
+                )
+            }
+            ConfigError::Invalid { field, reason } => write!(f, "invalid `{field}`: {reason}"),
+            ConfigError::NotFound { searched } => {
+                let searched: Vec<String> =
+                    searched.iter().map(|p| p.display().to_string()).collect();
+                write!(
+                    f,
+                    "no gateway config found (searched {})",
+                    searched.join(", ")
+                )
+            }
+            ConfigError::Load { path, reason } => {
+                write!(f, "cannot load config {}: {reason}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 impl AgentConfig {
+    #[cfg(feature = "toml")]
     pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
         toml::from_str(content)
     }
+
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+}
+
+/// Differences between the skills an agent was configured with and the
+/// capabilities it advertised on registration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReconcileReport {
+    /// Configured skills the agent did not advertise.
+    pub missing: Vec<String>,
+    /// Advertised capabilities that aren't in the config.
+    pub extra: Vec<String>,
+}
+
+impl ReconcileReport {
+    /// `true` when the agent advertised exactly its configured skills.
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// Result of [`GatewayConfig::diff`], for audit logs on config reload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// `server.host` or `server.port` changed.
+    pub server_changed: bool,
+    /// Providers only in the new config.
+    pub added: Vec<String>,
+    /// Providers only in the old config.
+    pub removed: Vec<String>,
+    /// Providers in both whose settings differ.
+    pub modified: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// `true` when neither the server nor any provider changed.
+    pub fn is_empty(&self) -> bool {
+        !self.server_changed
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+    }
+}
+
+/// Compare `config.skills` against `register.capabilities`, ignoring case.
+pub fn reconcile(config: &AgentConfig, register: &AgentRegister) -> ReconcileReport {
+    let configured: HashSet<String> = config.skills.iter().map(|s| s.to_lowercase()).collect();
+    let advertised: HashSet<String> = register
+        .capabilities
+        .iter()
+        .map(|c| c.to_lowercase())
+        .collect();
+    ReconcileReport {
+        missing: config
+            .skills
+            .iter()
+            .filter(|s| !advertised.contains(&s.to_lowercase()))
+            .cloned()
+            .collect(),
+        extra: register
+            .capabilities
+            .iter()
+            .filter(|c| !configured.contains(&c.to_lowercase()))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Hex-encoded SHA-256 of a value's JSON form with object keys sorted, so the
+/// same config always hashes the same regardless of field or map order.
+///
+/// This is the hash carried in `KingConfigUpdate.new_config_hash`; consumers
+/// recompute it with this function to detect changes.
+pub fn hash_config<T: Serialize>(config: &T) -> Result<String, serde_json::Error> {
+    let canonical = crate::canonical::to_canonical_json(config)?;
+    Ok(format!("{:x}", Sha256::digest(canonical.as_bytes())))
+}
+
+/// Find and parse the gateway config without an explicit path. Candidates,
+/// in order:
+///
+/// 1. `$EVO_CONFIG` — if set, the only candidate, so a typo fails loudly
+///    instead of falling through to another file
+/// 2. `./evo.toml`
+/// 3. `$XDG_CONFIG_HOME/evo/gateway.toml` (`$HOME/.config` when unset)
+/// 4. `/etc/evo/gateway.toml`
+///
+/// The first existing file wins. If it doesn't parse, that is an error
+/// rather than a reason to try the next one. Env overrides are not applied;
+/// see [`GatewayConfig::apply_env_overrides`].
+#[cfg(feature = "toml")]
+pub fn discover_gateway_config() -> Result<(PathBuf, GatewayConfig), ConfigError> {
+    load_first_gateway_config(&gateway_config_search_paths())
+}
+
+#[cfg(feature = "toml")]
+fn gateway_config_search_paths() -> Vec<PathBuf> {
+    if let Some(path) = std::env::var_os("EVO_CONFIG") {
+        return vec![PathBuf::from(path)];
+    }
+    let mut paths = vec![PathBuf::from("./evo.toml")];
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(dir) = config_home {
+        paths.push(dir.join("evo").join("gateway.toml"));
+    }
+    paths.push(PathBuf::from("/etc/evo/gateway.toml"));
+    paths
+}
+
+#[cfg(feature = "toml")]
+fn load_first_gateway_config(paths: &[PathBuf]) -> Result<(PathBuf, GatewayConfig), ConfigError> {
+    for path in paths {
+        let load_error = |reason: String| ConfigError::Load {
+            path: path.clone(),
+            reason,
+        };
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(load_error(e.to_string())),
+        };
+        let config = GatewayConfig::from_toml(&content).map_err(|e| load_error(e.to_string()))?;
+        return Ok((path.clone(), config));
+    }
+    Err(ConfigError::NotFound {
+        searched: paths.to_vec(),
+    })
+}
+
+/// JSON Schema (draft 2020-12) for `gateway.json`, for editor `$schema`
+/// references and pre-commit validation.
+///
+/// `server.port` is described as an integer, its canonical form, although
+/// numeric strings are also accepted when parsing.
+#[cfg(feature = "schemars")]
+pub fn gateway_config_schema() -> serde_json::Value {
+    schemars::schema_for!(GatewayConfig).into()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_env::EnvGuard;
 
     #[test]
+    #[cfg(feature = "toml")]
     fn parse_gateway_config_with_pool() {
         let toml_str = r#"
 [server]
@@ -227,6 +883,7 @@ provider_type = "open_ai_compatible"
     }
 
     #[test]
+    #[cfg(feature = "toml")]
     fn roundtrip_gateway_config_toml() {
         let config = GatewayConfig {
             server: ServerConfig {
@@ -239,10 +896,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec![],
                 enabled: true,
                 provider_type: ProviderType::OpenAiCompatible,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec![],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -267,10 +925,11 @@ provider_type = "open_ai_compatible"
                     api_key_envs: vec!["OPENAI_API_KEY".into()],
                     enabled: true,
                     provider_type: ProviderType::OpenAiCompatible,
-                    extra_headers: HashMap::new(),
+                    extra_headers: BTreeMap::new(),
                     rate_limit: None,
                     models: vec![],
                     model_metadata: None,
+                    health_check: None,
                 },
                 ProviderConfig {
                     name: "anthropic".into(),
@@ -278,10 +937,11 @@ provider_type = "open_ai_compatible"
                     api_key_envs: vec!["ANTHROPIC_API_KEY".into()],
                     enabled: true,
                     provider_type: ProviderType::Anthropic,
-                    extra_headers: HashMap::new(),
+                    extra_headers: BTreeMap::new(),
                     rate_limit: None,
                     models: vec![],
                     model_metadata: None,
+                    health_check: None,
                 },
             ],
             reliability: None,
@@ -308,10 +968,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec![],
                 enabled: false,
                 provider_type: ProviderType::ClaudeCode,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec![],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -335,10 +996,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec![],
                 enabled: false,
                 provider_type: ProviderType::CodexCli,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec![],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -362,10 +1024,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec![],
                 enabled: false,
                 provider_type: ProviderType::Cursor,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec![],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -389,10 +1052,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec![],
                 enabled: true,
                 provider_type: ProviderType::OpenAiCompatible,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec!["gpt-4o".into(), "gpt-4o-mini".into()],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -418,10 +1082,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec!["OPENAI_API_KEY".into()],
                 enabled: false,
                 provider_type: ProviderType::CodexAuth,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec![],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -460,10 +1125,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec!["GEMINI_API_KEY".into()],
                 enabled: false,
                 provider_type: ProviderType::Google,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec!["gemini-2.5-pro".into()],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -487,10 +1153,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec!["COPILOT_GITHUB_TOKEN".into()],
                 enabled: false,
                 provider_type: ProviderType::GithubCopilot,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec!["gpt-4o".into()],
                 model_metadata: None,
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -533,10 +1200,11 @@ provider_type = "open_ai_compatible"
                 api_key_envs: vec![],
                 enabled: true,
                 provider_type: ProviderType::OpenAiCompatible,
-                extra_headers: HashMap::new(),
+                extra_headers: BTreeMap::new(),
                 rate_limit: None,
                 models: vec!["gpt-4o".into()],
                 model_metadata: Some(metadata),
+                health_check: None,
             }],
             reliability: None,
             routing: None,
@@ -569,4 +1237,535 @@ provider_type = "open_ai_compatible"
         let config = GatewayConfig::from_json(json_str).unwrap();
         assert!(config.providers[0].model_metadata.is_none());
     }
+
+    #[test]
+    fn hash_config_ignores_map_order() {
+        let a: HashMap<&str, u32> = [("a", 1), ("b", 2), ("c", 3)].into_iter().collect();
+        let b: HashMap<&str, u32> = [("c", 3), ("b", 2), ("a", 1)].into_iter().collect();
+        let hash = hash_config(&a).unwrap();
+        assert_eq!(hash, hash_config(&b).unwrap());
+        assert_eq!(hash.len(), 64);
+        assert_ne!(hash, hash_config(&serde_json::json!({"a": 1})).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn roundtrip_provider_health_check() {
+        let json_str = r#"{
+            "server": { "host": "127.0.0.1", "port": 8080 },
+            "providers": [{
+                "name": "openai",
+                "base_url": "https://api.openai.com/v1",
+                "enabled": true,
+                "health_check": { "path": "/models" }
+            }]
+        }"#;
+        let config = GatewayConfig::from_json(json_str).unwrap();
+        let check = config.providers[0].health_check.as_ref().unwrap();
+        assert_eq!(check.path, "/models");
+        assert_eq!(check.expected_status, 200);
+        assert_eq!(check.interval_secs, 30);
+
+        let parsed = GatewayConfig::from_toml(&config.to_toml().unwrap()).unwrap();
+        assert_eq!(
+            parsed.providers[0].health_check.as_ref().unwrap().path,
+            "/models"
+        );
+    }
+
+    #[test]
+    fn health_url_joins_slashes() {
+        let mut provider = GatewayConfig::from_json(
+            r#"{"server":{"host":"","port":1},"providers":[{"name":"p","base_url":"http://localhost:11434/v1/","enabled":true}]}"#,
+        )
+        .unwrap()
+        .providers
+        .remove(0);
+        assert!(provider.health_url().is_none());
+
+        provider.health_check = Some(ProviderHealthCheck {
+            path: "/models".into(),
+            expected_status: 200,
+            interval_secs: 30,
+        });
+        assert_eq!(
+            provider.health_url().as_deref(),
+            Some("http://localhost:11434/v1/models")
+        );
+        provider.base_url = "http://localhost:11434/v1".into();
+        provider.health_check.as_mut().unwrap().path = "models".into();
+        assert_eq!(
+            provider.health_url().as_deref(),
+            Some("http://localhost:11434/v1/models")
+        );
+
+        provider.provider_type = ProviderType::ClaudeCode;
+        assert!(provider.health_url().is_none());
+    }
+
+    #[test]
+    fn gateway_summary_masks_secrets() {
+        let config = GatewayConfig::from_json(
+            r#"{
+                "server": { "host": "0.0.0.0", "port": 8080 },
+                "providers": [{
+                    "name": "openai",
+                    "base_url": "https://api.openai.com/v1",
+                    "api_key_envs": ["OPENAI_KEY_A", "OPENAI_KEY_B"],
+                    "enabled": true,
+                    "extra_headers": { "X-Title": "secret-title" }
+                }]
+            }"#,
+        )
+        .unwrap();
+        let summary = config.summary();
+        assert_eq!(
+            summary,
+            "openai [open_ai_compatible] https://api.openai.com/v1 keys=2 enabled"
+        );
+        assert!(!summary.contains("OPENAI_KEY_A"));
+        assert!(!summary.contains("secret-title"));
+    }
+
+    #[test]
+    fn reconcile_reports_missing_and_extra_skills() {
+        let config = AgentConfig {
+            role: "learning".into(),
+            skills: vec!["Web-Search".into(), "summarize".into()],
+            king_address: "http://localhost:3000".into(),
+        };
+        let register = AgentRegister {
+            agent_id: "learning-001".into(),
+            role: crate::messages::AgentRole::Learning,
+            capabilities: vec!["web-search".into(), "translate".into()],
+            protocol_version: None,
+        };
+        let report = reconcile(&config, &register);
+        assert_eq!(report.missing, vec!["summarize".to_string()]);
+        assert_eq!(report.extra, vec!["translate".to_string()]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn unknown_provider_type_is_rejected() {
+        let provider = |provider_type: Option<&str>| {
+            let mut json = serde_json::json!({
+                "name": "anthropic",
+                "base_url": "https://api.anthropic.com/v1",
+                "enabled": true,
+            });
+            if let Some(provider_type) = provider_type {
+                json["provider_type"] = provider_type.into();
+            }
+            serde_json::from_value::<ProviderConfig>(json)
+        };
+
+        let err = provider(Some("anthropc")).unwrap_err();
+        assert!(err.to_string().contains("unknown variant `anthropc`"));
+        assert_eq!(
+            provider(None).unwrap().provider_type,
+            ProviderType::OpenAiCompatible
+        );
+        assert_eq!(
+            provider(Some("anthropic")).unwrap().provider_type,
+            ProviderType::Anthropic
+        );
+    }
+
+    fn provider(name: &str, base_url: &str, api_key_envs: &[&str]) -> ProviderConfig {
+        ProviderConfig {
+            name: name.into(),
+            base_url: base_url.into(),
+            api_key_envs: api_key_envs.iter().map(|v| v.to_string()).collect(),
+            enabled: true,
+            provider_type: ProviderType::OpenAiCompatible,
+            extra_headers: BTreeMap::new(),
+            rate_limit: None,
+            models: vec![],
+            model_metadata: None,
+            health_check: None,
+        }
+    }
+
+    fn gateway(providers: Vec<ProviderConfig>) -> GatewayConfig {
+        GatewayConfig {
+            server: ServerConfig {
+                host: "0.0.0.0".into(),
+                port: 8080,
+            },
+            providers,
+            reliability: None,
+            routing: None,
+        }
+    }
+
+    #[test]
+    fn effective_config_resolves_keys_and_urls() {
+        let mut env = EnvGuard::new();
+        env.set("EVO_TEST_EFFECTIVE_KEY", "sk-test");
+        env.set("EVO_TEST_EFFECTIVE_HOST", "llm.internal");
+        let mut disabled = provider("off", "http://${EVO_TEST_UNSET_HOST}", &["EVO_TEST_UNSET"]);
+        disabled.enabled = false;
+        let config = gateway(vec![
+            provider(
+                "local",
+                "http://${EVO_TEST_EFFECTIVE_HOST}:8000/v1",
+                &["EVO_TEST_EFFECTIVE_KEY"],
+            ),
+            disabled,
+        ]);
+
+        let effective = config.effective().unwrap();
+        assert_eq!(effective.providers.len(), 1);
+        let local = &effective.providers[0];
+        assert_eq!(local.config.base_url, "http://llm.internal:8000/v1");
+        assert_eq!(local.api_keys, vec!["sk-test".to_string()]);
+        assert!(!format!("{local:?}").contains("sk-test"));
+    }
+
+    #[test]
+    fn env_overrides_replace_port_host_and_base_url() {
+        let mut config = gateway(vec![
+            provider("env-test", "http://localhost:8000/v1", &[]),
+            provider("untouched", "http://localhost:9000/v1", &[]),
+        ]);
+        let mut env = EnvGuard::new();
+        env.set("EVO_GATEWAY_PORT", "9090");
+        env.set("EVO_GATEWAY_HOST", "127.0.0.1");
+        env.set("EVO_PROVIDER_ENV_TEST_BASE_URL", "http://llm.prod:8000/v1");
+        config.apply_env_overrides();
+        env.set("EVO_GATEWAY_PORT", "not-a-port");
+        let mut bad_port = gateway(vec![]);
+        bad_port.apply_env_overrides();
+        drop(env);
+
+        assert_eq!(config.server.port, 9090);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.providers[0].base_url, "http://llm.prod:8000/v1");
+        assert_eq!(config.providers[1].base_url, "http://localhost:9000/v1");
+        assert_eq!(bad_port.server.port, 8080);
+    }
+
+    #[test]
+    fn effective_config_propagates_errors() {
+        let _env = EnvGuard::new();
+        let err = gateway(vec![provider("p", "http://x", &["EVO_TEST_UNSET_KEY"])])
+            .effective()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::MissingEnv {
+                provider: "p".into(),
+                var: "EVO_TEST_UNSET_KEY".into(),
+            }
+        );
+
+        let err = gateway(vec![
+            provider("p", "http://x", &[]),
+            provider("p", "http://y", &[]),
+        ])
+        .effective()
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ConfigError::Invalid {
+                field: "providers.name",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "schemars")]
+    fn gateway_schema_lists_provider_types_and_required_fields() {
+        let schema = gateway_config_schema();
+        let provider_type = schema["$defs"]["ProviderType"].to_string();
+        for name in [
+            "open_ai_compatible",
+            "anthropic",
+            "cursor",
+            "claude_code",
+            "codex_cli",
+            "codex_auth",
+            "google",
+            "github_copilot",
+            "custom",
+        ] {
+            assert!(provider_type.contains(&format!("\"{name}\"")), "{name}");
+        }
+        assert_eq!(
+            schema["$defs"]["ProviderConfig"]["required"],
+            serde_json::json!(["name", "base_url", "enabled"])
+        );
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["server", "providers"])
+        );
+    }
+
+    #[test]
+    fn gateway_diff_reports_provider_and_server_changes() {
+        let old = gateway(vec![
+            provider("openai", "https://api.openai.com/v1", &["OPENAI_KEY"]),
+            provider("ollama", "http://localhost:11434/v1", &[]),
+            provider("groq", "https://api.groq.com/v1", &["GROQ_KEY"]),
+        ]);
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = gateway(vec![
+            provider("openai", "https://api.openai.com/v1", &["OPENAI_KEY"]),
+            provider("groq", "https://api.groq.com/openai/v1", &["GROQ_KEY"]),
+            provider("anthropic", "https://api.anthropic.com", &["ANTHROPIC_KEY"]),
+        ]);
+        new.server.port = 9090;
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff,
+            ConfigDiff {
+                server_changed: true,
+                added: vec!["anthropic".into()],
+                removed: vec!["ollama".into()],
+                modified: vec!["groq".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn request_headers_for_openai_and_anthropic() {
+        let mut openai = provider("openrouter", "https://openrouter.ai/api/v1", &["KEY"]);
+        openai.extra_headers = BTreeMap::from([
+            ("HTTP-Referer".to_string(), "https://evo.dev".to_string()),
+            ("authorization".to_string(), "Bearer stale".to_string()),
+        ]);
+        assert_eq!(
+            openai.request_headers(Some("sk-or")),
+            vec![
+                ("http-referer".to_string(), "https://evo.dev".to_string()),
+                ("authorization".to_string(), "Bearer sk-or".to_string()),
+            ]
+        );
+
+        let mut anthropic = provider("anthropic", "https://api.anthropic.com", &["KEY"]);
+        anthropic.provider_type = ProviderType::Anthropic;
+        assert_eq!(
+            anthropic.request_headers(Some("sk-ant")),
+            vec![
+                ("anthropic-version".to_string(), "2023-06-01".to_string()),
+                ("x-api-key".to_string(), "sk-ant".to_string()),
+            ]
+        );
+        anthropic.extra_headers =
+            BTreeMap::from([("Anthropic-Version".to_string(), "2024-01-01".to_string())]);
+        assert_eq!(
+            anthropic.request_headers(None),
+            vec![("anthropic-version".to_string(), "2024-01-01".to_string())]
+        );
+
+        anthropic.provider_type = ProviderType::ClaudeCode;
+        assert!(anthropic.request_headers(Some("sk-ant")).is_empty());
+    }
+
+    #[test]
+    fn validate_requires_models_for_enabled_cli_providers() {
+        let mut cli = provider("claude", "", &[]);
+        cli.provider_type = ProviderType::ClaudeCode;
+        let err = gateway(vec![cli.clone()]).validate().unwrap_err();
+        assert!(matches!(
+            &err,
+            ConfigError::Invalid { field: "providers.models", reason } if reason.contains("`claude`")
+        ));
+
+        // API providers can discover models upstream; disabled CLIs are ignored.
+        cli.enabled = false;
+        gateway(vec![cli.clone(), provider("openai", "http://x", &[])])
+            .validate()
+            .unwrap();
+
+        cli.enabled = true;
+        cli.models = vec!["claude-sonnet".into()];
+        gateway(vec![cli]).validate().unwrap();
+    }
+
+    #[test]
+    fn server_port_accepts_numeric_strings() {
+        let parse = |port: serde_json::Value| {
+            serde_json::from_value::<ServerConfig>(
+                serde_json::json!({ "host": "0.0.0.0", "port": port }),
+            )
+        };
+        assert_eq!(parse(serde_json::json!(8080)).unwrap().port, 8080);
+        assert_eq!(parse(serde_json::json!("8080")).unwrap().port, 8080);
+        assert!(
+            parse(serde_json::json!("http"))
+                .unwrap_err()
+                .to_string()
+                .contains("invalid port `http`")
+        );
+        assert!(parse(serde_json::json!(70000)).is_err());
+
+        let json = serde_json::to_value(parse(serde_json::json!("8080")).unwrap()).unwrap();
+        assert_eq!(json["port"], 8080);
+
+        #[cfg(feature = "toml")]
+        {
+            let from_int: ServerConfig = toml::from_str("host = \"h\"\nport = 8080").unwrap();
+            let from_str: ServerConfig = toml::from_str("host = \"h\"\nport = \"8080\"").unwrap();
+            assert_eq!(from_int.port, from_str.port);
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    fn discovery_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("evo-common-discover-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(feature = "toml")]
+    fn write_gateway(path: &std::path::Path, port: u16) {
+        std::fs::write(
+            path,
+            format!("providers = []\n[server]\nhost = \"0.0.0.0\"\nport = {port}\n"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn discovery_takes_first_existing_path() {
+        let dir = discovery_dir("precedence");
+        let (local, xdg, etc) = (
+            dir.join("evo.toml"),
+            dir.join("xdg.toml"),
+            dir.join("etc.toml"),
+        );
+        write_gateway(&xdg, 2);
+        write_gateway(&etc, 3);
+        let candidates = [local.clone(), xdg.clone(), etc.clone()];
+
+        let (path, config) = load_first_gateway_config(&candidates).unwrap();
+        assert_eq!((path, config.server.port), (xdg.clone(), 2));
+
+        write_gateway(&local, 1);
+        let (path, config) = load_first_gateway_config(&candidates).unwrap();
+        assert_eq!((path, config.server.port), (local, 1));
+
+        std::fs::remove_file(&xdg).unwrap();
+        let (path, _) = load_first_gateway_config(&candidates[1..]).unwrap();
+        assert_eq!(path, etc);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn discovery_errors_list_paths_and_stop_at_bad_file() {
+        let dir = discovery_dir("errors");
+        let candidates = [dir.join("a.toml"), dir.join("b.toml")];
+        let err = load_first_gateway_config(&candidates).unwrap_err();
+        assert_eq!(
+            err,
+            ConfigError::NotFound {
+                searched: candidates.to_vec()
+            }
+        );
+        let message = err.to_string();
+        assert!(message.starts_with("no gateway config found"));
+        assert!(message.contains("a.toml") && message.contains("b.toml"));
+
+        std::fs::write(&candidates[0], "not = [valid").unwrap();
+        write_gateway(&candidates[1], 8080);
+        assert!(matches!(
+            load_first_gateway_config(&candidates),
+            Err(ConfigError::Load { path, .. }) if path == candidates[0]
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn discovery_search_paths_honor_env() {
+        let dir = discovery_dir("env");
+        let explicit = dir.join("explicit.toml");
+        write_gateway(&explicit, 7000);
+
+        let mut env = EnvGuard::new();
+        env.remove("EVO_CONFIG");
+        env.set("XDG_CONFIG_HOME", &dir);
+        let default_paths = gateway_config_search_paths();
+        env.set("EVO_CONFIG", &explicit);
+        let env_paths = gateway_config_search_paths();
+        let discovered = discover_gateway_config();
+        drop(env);
+
+        assert_eq!(
+            default_paths,
+            [
+                PathBuf::from("./evo.toml"),
+                dir.join("evo").join("gateway.toml"),
+                PathBuf::from("/etc/evo/gateway.toml"),
+            ]
+        );
+        assert_eq!(env_paths, std::slice::from_ref(&explicit));
+        let (path, config) = discovered.unwrap();
+        assert_eq!((path, config.server.port), (explicit, 7000));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn provider_type_labels() {
+        let cases = [
+            (
+                ProviderType::OpenAiCompatible,
+                "open_ai_compatible",
+                "OpenAI-compatible",
+            ),
+            (ProviderType::Anthropic, "anthropic", "Anthropic"),
+            (ProviderType::Cursor, "cursor", "Cursor"),
+            (ProviderType::ClaudeCode, "claude_code", "Claude Code"),
+            (ProviderType::CodexCli, "codex_cli", "Codex CLI"),
+            (ProviderType::CodexAuth, "codex_auth", "Codex (OAuth)"),
+            (ProviderType::Google, "google", "Google Gemini"),
+            (
+                ProviderType::GithubCopilot,
+                "github_copilot",
+                "GitHub Copilot",
+            ),
+        ];
+        for (provider_type, serde_name, display_name) in cases {
+            assert_eq!(provider_type.as_str(), serde_name);
+            assert_eq!(
+                serde_json::to_value(&provider_type).unwrap(),
+                serde_json::json!(serde_name)
+            );
+            assert_eq!(provider_type.display_name(), display_name);
+            assert_eq!(provider_type.to_string(), display_name);
+        }
+
+        let custom = ProviderType::Custom("anthropic".into());
+        assert_eq!(custom.as_str(), "anthropic");
+        assert_eq!(custom.display_name(), "anthropic");
+        assert_eq!(custom.to_string(), "custom:anthropic");
+        assert_ne!(custom.to_string(), ProviderType::Anthropic.to_string());
+        assert_eq!(
+            serde_json::to_value(&custom).unwrap(),
+            serde_json::json!({ "custom": "anthropic" })
+        );
+    }
+
+    #[test]
+    fn provider_auth_headers() {
+        assert_eq!(
+            ProviderType::OpenAiCompatible.format_auth("sk-1"),
+            Some(("Authorization".to_string(), "Bearer sk-1".to_string()))
+        );
+        assert_eq!(
+            ProviderType::Anthropic.format_auth("sk-ant"),
+            Some(("x-api-key".to_string(), "sk-ant".to_string()))
+        );
+        assert_eq!(ProviderType::ClaudeCode.auth_header_name(), None);
+        assert_eq!(ProviderType::Cursor.format_auth("k"), None);
+    }
 }
@@ -1,6 +1,76 @@
+pub mod canonical;
 pub mod config;
 pub mod logging;
 pub mod messages;
+#[cfg(feature = "metrics-otel")]
+pub mod metrics;
+pub mod prelude;
+pub mod providers;
+#[cfg(feature = "signing")]
+pub mod signing;
 pub mod skill;
+#[cfg(feature = "bincode")]
+pub mod storage;
 #[cfg(feature = "tracing-otel")]
 pub mod tracing_context;
+
+/// Paths used by exported macros; not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json::Value;
+}
+
+/// Serialises tests that touch process environment variables, which are
+/// shared across the parallel test threads.
+#[cfg(test)]
+pub(crate) mod test_env {
+    use std::ffi::{OsStr, OsString};
+    use std::sync::{Mutex, MutexGuard};
+
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    /// Holds the env lock and restores every variable it changed on drop.
+    /// Tests that only read the environment still take one so no writer
+    /// runs underneath them.
+    pub(crate) struct EnvGuard {
+        saved: Vec<(String, Option<OsString>)>,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl EnvGuard {
+        pub(crate) fn new() -> Self {
+            Self {
+                saved: Vec::new(),
+                // A panicking test must not fail every later one.
+                _lock: LOCK.lock().unwrap_or_else(|e| e.into_inner()),
+            }
+        }
+
+        pub(crate) fn set(&mut self, key: &str, value: impl AsRef<OsStr>) {
+            self.save(key);
+            unsafe { std::env::set_var(key, value) };
+        }
+
+        pub(crate) fn remove(&mut self, key: &str) {
+            self.save(key);
+            unsafe { std::env::remove_var(key) };
+        }
+
+        fn save(&mut self, key: &str) {
+            if !self.saved.iter().any(|(k, _)| k == key) {
+                self.saved.push((key.to_string(), std::env::var_os(key)));
+            }
+        }
+    }
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            for (key, value) in self.saved.drain(..).rev() {
+                match value {
+                    Some(value) => unsafe { std::env::set_var(&key, value) },
+                    None => unsafe { std::env::remove_var(&key) },
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,17 @@
+use crate::messages::AgentRole;
 use std::env;
+use std::fmt as std_fmt;
+use std::io;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tracing::span::EnteredSpan;
+use tracing::{Event, Subscriber};
 use tracing_appender::non_blocking::WorkerGuard;
-use tracing_subscriber::EnvFilter;
 use tracing_subscriber::fmt;
+use tracing_subscriber::layer::{Context, Layer};
 use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry, reload};
 
 const DEFAULT_LOG_DIR: &str = "./logs";
 const ENV_LOG_DIR: &str = "EVO_LOG_DIR";
@@ -14,9 +22,230 @@ pub fn log_dir() -> PathBuf {
         .unwrap_or_else(|_| PathBuf::from(DEFAULT_LOG_DIR))
 }
 
+/// Why [`check_log_dir`] rejected the log directory.
+#[derive(Debug)]
+pub enum LogDirError {
+    /// The directory doesn't exist and couldn't be created.
+    Create { path: PathBuf, source: io::Error },
+    /// The directory exists but a file couldn't be written to it.
+    NotWritable { path: PathBuf, source: io::Error },
+}
+
+impl std_fmt::Display for LogDirError {
+    fn fmt(&self, f: &mut std_fmt::Formatter<'_>) -> std_fmt::Result {
+        match self {
+            LogDirError::Create { path, source } => {
+                write!(
+                    f,
+                    "cannot create log directory {}: {source}",
+                    path.display()
+                )
+            }
+            LogDirError::NotWritable { path, source } => {
+                write!(
+                    f,
+                    "log directory {} is not writable: {source}",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LogDirError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LogDirError::Create { source, .. } | LogDirError::NotWritable { source, .. } => {
+                Some(source)
+            }
+        }
+    }
+}
+
+/// Resolve [`log_dir`], create it if missing, and confirm it is writable by
+/// writing and removing a probe file.
+pub fn check_log_dir() -> Result<PathBuf, LogDirError> {
+    let path = log_dir();
+    if let Err(source) = std::fs::create_dir_all(&path) {
+        return Err(LogDirError::Create { path, source });
+    }
+    let probe = path.join(format!(".evo-write-probe-{}", std::process::id()));
+    if let Err(source) = std::fs::write(&probe, b"").and_then(|()| std::fs::remove_file(&probe)) {
+        return Err(LogDirError::NotWritable { path, source });
+    }
+    Ok(path)
+}
+
+/// Changes the active log filter of an initialized logger at runtime, e.g. in
+/// response to a `set_log_level` king command.
+#[derive(Clone)]
+pub struct LogReloadHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogReloadHandle {
+    /// Replace the filter with `directives` in `RUST_LOG` syntax, e.g.
+    /// `"debug"` or `"info,evo_common=trace"`.
+    pub fn set_filter(&self, directives: &str) -> Result<(), LogFilterError> {
+        let filter = EnvFilter::try_new(directives).map_err(LogFilterError::Parse)?;
+        self.0.reload(filter).map_err(LogFilterError::Reload)
+    }
+}
+
+/// Why [`LogReloadHandle::set_filter`] failed.
+#[derive(Debug)]
+pub enum LogFilterError {
+    /// The directives aren't valid `EnvFilter` syntax.
+    Parse(tracing_subscriber::filter::ParseError),
+    /// The subscriber the handle belongs to no longer exists.
+    Reload(reload::Error),
+}
+
+impl std_fmt::Display for LogFilterError {
+    fn fmt(&self, f: &mut std_fmt::Formatter<'_>) -> std_fmt::Result {
+        match self {
+            LogFilterError::Parse(e) => write!(f, "invalid log filter: {e}"),
+            LogFilterError::Reload(e) => write!(f, "failed to reload log filter: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LogFilterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LogFilterError::Parse(e) => Some(e),
+            LogFilterError::Reload(e) => Some(e),
+        }
+    }
+}
+
+static RELOAD_HANDLE: OnceLock<LogReloadHandle> = OnceLock::new();
+
+/// Handle to the filter installed by [`init_logging`] (or
+/// `init_logging_with_otel`); `None` before logging is initialized.
+pub fn log_reload_handle() -> Option<LogReloadHandle> {
+    RELOAD_HANDLE.get().cloned()
+}
+
+/// Dependencies whose debug output drowns our own; [`default_filter`] caps
+/// them at `warn`.
+const NOISY_TARGETS: &[&str] = &["hyper", "h2", "tower", "reqwest", "rustls"];
+
+/// Filter setting this crate's target (`evo_common`) to `level` and pinning
+/// noisy HTTP/TLS dependencies (`hyper`, `h2`, `tower`, `reqwest`, `rustls`)
+/// to `warn`. Other targets, such as the calling service's own, also log at
+/// `level`. Used by the initializers when `RUST_LOG` is unset.
+pub fn default_filter(level: &str) -> EnvFilter {
+    let mut directives = format!("{level},{}={level}", env!("CARGO_CRATE_NAME"));
+    for target in NOISY_TARGETS {
+        directives.push_str(&format!(",{target}=warn"));
+    }
+    EnvFilter::new(directives)
+}
+
+/// Lets through only every `one_in`-th event for `target` (and its
+/// submodules, e.g. `evo_runner::output` also covers
+/// `evo_runner::output::chunk`); other targets are unaffected. The first
+/// event is always emitted.
+///
+/// Filtering happens before any output layer sees the event, so it applies to
+/// the file and stdout alike. Install it with [`init_logging_with_sampling`].
+#[derive(Debug)]
+pub struct SamplingLayer {
+    target: String,
+    one_in: u64,
+    seen: AtomicU64,
+}
+
+impl SamplingLayer {
+    /// Sample `target` at one event in `one_in`; `0` is treated as `1`
+    /// (no sampling).
+    pub fn new(target: &str, one_in: u32) -> Self {
+        Self {
+            target: target.to_string(),
+            one_in: u64::from(one_in.max(1)),
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    fn matches(&self, target: &str) -> bool {
+        target
+            .strip_prefix(self.target.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SamplingLayer {
+    fn event_enabled(&self, event: &Event<'_>, _ctx: Context<'_, S>) -> bool {
+        if !self.matches(event.metadata().target()) {
+            return true;
+        }
+        self.seen
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(self.one_in)
+    }
+}
+
+/// `RUST_LOG` filter (default [`default_filter`]`("info")`) wrapped so it can
+/// be swapped at runtime.
+fn reloadable_env_filter() -> (reload::Layer<EnvFilter, Registry>, LogReloadHandle) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| default_filter("info"));
+    let (layer, handle) = reload::Layer::new(filter);
+    (layer, LogReloadHandle(handle))
+}
+
+/// Set by the first logging initializer; the global subscriber, propagator
+/// and tracer provider can only be installed once per process.
+static LOGGING_INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// `true` if this is the first initializer call in the process.
+fn claim_logging_init() -> bool {
+    !LOGGING_INITIALIZED.swap(true, Ordering::SeqCst)
+}
+
+/// Guard for a second initializer call: owns an appender writing nowhere.
+fn inert_worker_guard() -> WorkerGuard {
+    tracing_appender::non_blocking(std::io::sink()).1
+}
+
+/// Install the global subscriber: JSON to `{log_dir}/{component}.log` plus
+/// plain text to stdout.
+///
+/// Logging is process-global and initialized once; later calls to this or
+/// `init_logging_with_otel` are no-ops returning an inert guard.
+///
+/// # Panics
+///
+/// If the log directory is unusable; see [`try_init_logging`].
 pub fn init_logging(component: &str) -> WorkerGuard {
-    let dir = log_dir();
-    std::fs::create_dir_all(&dir).expect("Failed to create log directory");
+    try_init_logging(component).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Like [`init_logging`], but runs [`check_log_dir`] first and returns its
+/// error instead of panicking. Nothing is installed on error.
+pub fn try_init_logging(component: &str) -> Result<WorkerGuard, LogDirError> {
+    try_init_logging_inner(component, None)
+}
+
+/// Like [`init_logging`], with `sampling` thinning out a high-frequency
+/// target before it reaches the file and stdout layers.
+///
+/// # Panics
+///
+/// If the log directory is unusable; see [`check_log_dir`].
+pub fn init_logging_with_sampling(component: &str, sampling: SamplingLayer) -> WorkerGuard {
+    try_init_logging_inner(component, Some(sampling)).unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn try_init_logging_inner(
+    component: &str,
+    sampling: Option<SamplingLayer>,
+) -> Result<WorkerGuard, LogDirError> {
+    if LOGGING_INITIALIZED.load(Ordering::SeqCst) {
+        return Ok(inert_worker_guard());
+    }
+    let dir = check_log_dir()?;
+    if !claim_logging_init() {
+        return Ok(inert_worker_guard());
+    }
 
     let file_appender = tracing_appender::rolling::daily(&dir, format!("{component}.log"));
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
@@ -31,15 +260,35 @@ pub fn init_logging(component: &str) -> WorkerGuard {
 
     let stdout_layer = fmt::layer().with_target(true).with_thread_ids(false);
 
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, reload_handle) = reloadable_env_filter();
 
     tracing_subscriber::registry()
         .with(filter)
+        .with(sampling)
         .with(file_layer)
         .with(stdout_layer)
         .init();
+    let _ = RELOAD_HANDLE.set(reload_handle);
+
+    Ok(guard)
+}
 
-    guard
+/// Enter an `info` span carrying the agent's identity, so every event logged
+/// while it is entered inherits `agent_id` and `role` in the JSON output.
+///
+/// `run_id` is declared empty and can be filled in later with
+/// `Span::current().record("run_id", ...)` once a pipeline run starts.
+///
+/// The returned guard **must** be held for as long as the fields should apply;
+/// dropping it exits the span.
+pub fn with_agent_context(agent_id: &str, role: &AgentRole) -> EnteredSpan {
+    tracing::info_span!(
+        "agent",
+        agent_id,
+        role = role.as_str(),
+        run_id = tracing::field::Empty
+    )
+    .entered()
 }
 
 // ─── OpenTelemetry integration (behind "tracing-otel" feature) ────────────────
@@ -49,11 +298,28 @@ pub struct OtelGuard {
     provider: opentelemetry_sdk::trace::SdkTracerProvider,
 }
 
+#[cfg(feature = "tracing-otel")]
+impl OtelGuard {
+    /// Export all buffered spans now, e.g. before a long teardown sequence
+    /// that might not complete.
+    pub fn force_flush(&self) -> opentelemetry_sdk::error::OTelSdkResult {
+        self.provider.force_flush()
+    }
+
+    /// Flush and shut down the tracer provider ahead of `Drop`. Spans created
+    /// afterwards are discarded.
+    pub fn shutdown(&self) -> opentelemetry_sdk::error::OTelSdkResult {
+        self.provider.shutdown()
+    }
+}
+
 #[cfg(feature = "tracing-otel")]
 impl Drop for OtelGuard {
     fn drop(&mut self) {
-        if let Err(e) = self.provider.shutdown() {
-            eprintln!("OpenTelemetry shutdown error: {e}");
+        match self.provider.shutdown() {
+            // Already shut down explicitly via `OtelGuard::shutdown`.
+            Ok(()) | Err(opentelemetry_sdk::error::OTelSdkError::AlreadyShutdown) => {}
+            Err(e) => eprintln!("OpenTelemetry shutdown error: {e}"),
         }
     }
 }
@@ -68,6 +334,10 @@ impl Drop for OtelGuard {
 /// Returns two guards that **must** be held for the process lifetime:
 /// * `WorkerGuard` – flushes the non-blocking file appender on drop.
 /// * `OtelGuard`   – shuts down the tracer provider on drop.
+///
+/// Only the first logging initializer in a process takes effect. A later call
+/// (e.g. a second component started in the same test harness) installs
+/// nothing and returns inert guards that export nothing.
 #[cfg(feature = "tracing-otel")]
 pub fn init_logging_with_otel(component: &str, otlp_endpoint: &str) -> (WorkerGuard, OtelGuard) {
     use opentelemetry::global;
@@ -78,6 +348,11 @@ pub fn init_logging_with_otel(component: &str, otlp_endpoint: &str) -> (WorkerGu
     use opentelemetry_sdk::trace::SdkTracerProvider;
     use tracing_opentelemetry::OpenTelemetryLayer;
 
+    if !claim_logging_init() {
+        let provider = SdkTracerProvider::builder().build();
+        return (inert_worker_guard(), OtelGuard { provider });
+    }
+
     // W3C Trace-Context propagator (traceparent / tracestate headers)
     global::set_text_map_propagator(TraceContextPropagator::new());
 
@@ -102,8 +377,7 @@ pub fn init_logging_with_otel(component: &str, otlp_endpoint: &str) -> (WorkerGu
     let otel_layer = OpenTelemetryLayer::new(provider.tracer(component.to_owned()));
 
     // File + stdout layers (identical to `init_logging`)
-    let dir = log_dir();
-    std::fs::create_dir_all(&dir).expect("Failed to create log directory");
+    let dir = check_log_dir().unwrap_or_else(|e| panic!("{e}"));
     let file_appender = tracing_appender::rolling::daily(&dir, format!("{component}.log"));
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
 
@@ -117,7 +391,7 @@ pub fn init_logging_with_otel(component: &str, otlp_endpoint: &str) -> (WorkerGu
 
     let stdout_layer = fmt::layer().with_target(true).with_thread_ids(false);
 
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, reload_handle) = reloadable_env_filter();
 
     tracing_subscriber::registry()
         .with(filter)
@@ -125,6 +399,7 @@ pub fn init_logging_with_otel(component: &str, otlp_endpoint: &str) -> (WorkerGu
         .with(stdout_layer)
         .with(otel_layer)
         .init();
+    let _ = RELOAD_HANDLE.set(reload_handle);
 
     (guard, OtelGuard { provider })
 }
@@ -132,23 +407,187 @@ pub fn init_logging_with_otel(component: &str, otlp_endpoint: &str) -> (WorkerGu
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_env::EnvGuard;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    /// In-memory writer so tests can inspect formatted log output.
+    #[derive(Clone, Default)]
+    struct CaptureWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl CaptureWriter {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl io::Write for CaptureWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
 
-    // Serialise env-var mutation tests so parallel test threads don't race.
-    static ENV_MUTEX: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for CaptureWriter {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
 
     #[test]
     fn default_log_dir() {
-        let _guard = ENV_MUTEX.lock().unwrap();
-        unsafe { env::remove_var(ENV_LOG_DIR) };
+        let mut env = EnvGuard::new();
+        env.remove(ENV_LOG_DIR);
         assert_eq!(log_dir(), PathBuf::from("./logs"));
     }
 
     #[test]
     fn custom_log_dir() {
-        let _guard = ENV_MUTEX.lock().unwrap();
-        unsafe { env::set_var(ENV_LOG_DIR, "/tmp/evo-test-logs") };
-        let result = log_dir();
-        unsafe { env::remove_var(ENV_LOG_DIR) };
-        assert_eq!(result, PathBuf::from("/tmp/evo-test-logs"));
+        let mut env = EnvGuard::new();
+        env.set(ENV_LOG_DIR, "/tmp/evo-test-logs");
+        assert_eq!(log_dir(), PathBuf::from("/tmp/evo-test-logs"));
+    }
+
+    #[test]
+    fn check_log_dir_creates_writable_dir() {
+        let dir = env::temp_dir().join(format!("evo-common-log-check-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut env = EnvGuard::new();
+        env.set(ENV_LOG_DIR, &dir);
+        assert_eq!(check_log_dir().unwrap(), dir);
+        // The probe file is cleaned up.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_log_dir_rejects_path_under_file() {
+        let file = env::temp_dir().join(format!("evo-common-log-file-{}", std::process::id()));
+        std::fs::write(&file, b"not a directory").unwrap();
+        let mut env = EnvGuard::new();
+        env.set(ENV_LOG_DIR, file.join("logs"));
+        let result = check_log_dir();
+        std::fs::remove_file(&file).unwrap();
+        let err = result.unwrap_err();
+        assert!(matches!(&err, LogDirError::Create { path, .. } if *path == file.join("logs")));
+        assert!(err.to_string().starts_with("cannot create log directory"));
+    }
+
+    #[test]
+    fn agent_context_fields_reach_nested_events() {
+        let writer = CaptureWriter::default();
+        let subscriber =
+            tracing_subscriber::registry().with(fmt::layer().json().with_writer(writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _ctx = with_agent_context("learning-001", &AgentRole::Learning);
+            tracing::info!("discovering skills");
+        });
+
+        let line = writer.contents();
+        let event: serde_json::Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(event["span"]["agent_id"], "learning-001");
+        assert_eq!(event["span"]["role"], "learning");
+    }
+
+    #[test]
+    fn reload_handle_changes_captured_level() {
+        let writer = CaptureWriter::default();
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        let handle = LogReloadHandle(handle);
+        let subscriber = tracing_subscriber::registry()
+            .with(filter)
+            .with(fmt::layer().with_writer(writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::trace!("hidden at info");
+            handle.set_filter("trace").unwrap();
+            tracing::trace!("visible at trace");
+        });
+
+        let output = writer.contents();
+        assert!(!output.contains("hidden at info"));
+        assert!(output.contains("visible at trace"));
+        assert!(matches!(
+            handle.set_filter("info,=bogus[["),
+            Err(LogFilterError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn default_filter_quiets_noisy_targets() {
+        let filter = default_filter("debug").to_string();
+        assert!(filter.contains("evo_common=debug"), "{filter}");
+        assert!(filter.contains("hyper=warn"), "{filter}");
+
+        let writer = CaptureWriter::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(default_filter("debug"))
+            .with(fmt::layer().with_writer(writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "evo_common::logging", "ours at debug");
+            tracing::debug!(target: "hyper::proto", "hyper at debug");
+            tracing::warn!(target: "hyper::proto", "hyper at warn");
+        });
+
+        let output = writer.contents();
+        assert!(output.contains("ours at debug"));
+        assert!(!output.contains("hyper at debug"));
+        assert!(output.contains("hyper at warn"));
+    }
+
+    #[test]
+    fn sampling_layer_passes_one_in_n() {
+        let writer = CaptureWriter::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(SamplingLayer::new("evo_runner::output", 10))
+            .with(fmt::layer().with_writer(writer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..100 {
+                tracing::info!(target: "evo_runner::output::chunk", "chunk {i}");
+            }
+            for i in 0..5 {
+                tracing::info!(target: "evo_runner::outputs", "unsampled {i}");
+            }
+        });
+
+        let output = writer.contents();
+        assert_eq!(output.matches("chunk ").count(), 10);
+        assert!(output.contains("chunk 0"));
+        assert!(output.contains("chunk 10"));
+        assert!(!output.contains("chunk 1\n"));
+        assert_eq!(output.matches("unsampled ").count(), 5);
+    }
+
+    #[cfg(feature = "tracing-otel")]
+    #[test]
+    fn otel_init_twice_does_not_panic() {
+        let dir = std::env::temp_dir().join("evo-common-otel-double-init");
+        let mut env = EnvGuard::new();
+        env.set(ENV_LOG_DIR, &dir);
+        let first = init_logging_with_otel("first", "http://127.0.0.1:4318");
+        let second = init_logging_with_otel("second", "http://127.0.0.1:4318");
+        assert!(log_reload_handle().is_some());
+        drop(second);
+        drop(first);
+    }
+
+    #[cfg(feature = "tracing-otel")]
+    #[test]
+    fn otel_guard_force_flush_and_shutdown() {
+        let guard = OtelGuard {
+            provider: opentelemetry_sdk::trace::SdkTracerProvider::builder().build(),
+        };
+        guard.force_flush().unwrap();
+        guard.shutdown().unwrap();
     }
 }
@@ -1,28 +1,465 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// ─── Identifiers ─────────────────────────────────────────────────────────────
+
+/// Declares a transparent `String` newtype for an id, so different kinds of
+/// ids can't be mixed up while the wire format stays a plain JSON string.
+macro_rules! string_id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new(id: impl Into<String>) -> Self {
+                Self(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> String {
+                self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                Self(id.to_string())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+/// Implements `as_str`, `Display`, and `FromStr` for a unit-only enum, using
+/// the same snake_case labels serde uses on the wire.
+macro_rules! enum_labels {
+    ($name:ident { $($variant:ident => $label:literal),+ $(,)? }) => {
+        impl $name {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $label,)+
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseEnumError;
+
+            /// Accepts the snake_case label or its kebab-case spelling.
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let normalized = s.replace('-', "_");
+                match normalized.as_str() {
+                    $($label => Ok($name::$variant),)+
+                    _ => Err(ParseEnumError {
+                        enum_name: stringify!($name),
+                        value: s.to_string(),
+                        expected: &[$($label),+],
+                    }),
+                }
+            }
+        }
+    };
+}
+
+/// Returned when a string isn't one of an enum's snake_case labels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEnumError {
+    pub enum_name: &'static str,
+    pub value: String,
+    pub expected: &'static [&'static str],
+}
+
+impl fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown {} `{}` (expected one of: {})",
+            self.enum_name,
+            self.value,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseEnumError {}
+
+string_id!(
+    /// Identifies a connected agent runner (e.g. `"learning-001"`).
+    AgentId
+);
+string_id!(
+    /// Identifies a task managed by king.
+    TaskId
+);
+string_id!(
+    /// Identifies a single pipeline run.
+    RunId
+);
+string_id!(
+    /// Identifies a skill by name.
+    SkillId
+);
+
+// ─── Validation ──────────────────────────────────────────────────────────────
+
+/// A message that parsed fine but breaks a protocol rule serde can't express.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &'static str, reason: impl Into<String>) -> Self {
+        Self {
+            field,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid `{}`: {}", self.field, self.reason)
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Version of the king ↔ agent message protocol defined in this module.
+/// Bump whenever a change would make older peers misread messages.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Whether a peer speaking `their_version` can talk to this build.
+pub fn is_compatible(their_version: u32) -> bool {
+    their_version == PROTOCOL_VERSION
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentRegister {
-    pub agent_id: String,
+    pub agent_id: AgentId,
     pub role: AgentRole,
     pub capabilities: Vec<String>,
+    /// Protocol version the agent was built against; `None` for agents that
+    /// predate versioning.
+    #[serde(default)]
+    pub protocol_version: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AgentRegister {
+    /// Check `agent_id` is non-empty lowercase ASCII letters, digits and `-`,
+    /// capabilities are unique, and a `User` role passes [`AgentRole::user`].
+    /// Reports every violation, not just the first.
+    pub fn validate(&self) -> Result<(), RegisterError> {
+        let mut violations = Vec::new();
+        let agent_id = self.agent_id.as_str();
+        if agent_id.is_empty() {
+            violations.push(ValidationError::new("agent_id", "must not be empty"));
+        } else if !agent_id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            violations.push(ValidationError::new(
+                "agent_id",
+                format!("`{agent_id}` may only contain a-z, 0-9 and `-`"),
+            ));
+        }
+        for (i, capability) in self.capabilities.iter().enumerate() {
+            if self.capabilities[..i].contains(capability) {
+                violations.push(ValidationError::new(
+                    "capabilities",
+                    format!("duplicate capability `{capability}`"),
+                ));
+            }
+        }
+        if let AgentRole::User(name) = &self.role
+            && let Err(e) = AgentRole::user(name)
+        {
+            violations.push(ValidationError::new("role", e.to_string()));
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(RegisterError { violations })
+        }
+    }
+}
+
+/// Every rule an [`AgentRegister`] broke, in field order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisterError {
+    pub violations: Vec<ValidationError>,
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid agent registration: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentStatus {
-    pub agent_id: String,
+    pub agent_id: AgentId,
     pub status: RunnerStatus,
-    pub metrics: HashMap<String, serde_json::Value>,
+    pub metrics: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AgentStatus {
+    /// Changes needed to turn `prev` into `self`, for sending as an
+    /// [`AgentStatusDelta`] instead of the full status.
+    pub fn diff(&self, prev: &AgentStatus) -> AgentStatusDelta {
+        let changed_metrics = self
+            .metrics
+            .iter()
+            .filter(|(key, value)| prev.metrics.get(*key) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        let mut removed_metrics: Vec<String> = prev
+            .metrics
+            .keys()
+            .filter(|key| !self.metrics.contains_key(*key))
+            .cloned()
+            .collect();
+        removed_metrics.sort();
+        AgentStatusDelta {
+            agent_id: self.agent_id.clone(),
+            status: (self.status != prev.status).then(|| self.status.clone()),
+            changed_metrics,
+            removed_metrics,
+        }
+    }
+}
+
+/// Compact `agent:status_delta` update carrying only what changed since the
+/// agent's last full [`AgentStatus`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentStatusDelta {
+    pub agent_id: AgentId,
+    /// New runner status, or `None` if unchanged.
+    #[serde(default)]
+    pub status: Option<RunnerStatus>,
+    /// Metrics that were added or whose value changed.
+    #[serde(default)]
+    pub changed_metrics: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub removed_metrics: Vec<String>,
+}
+
+impl AgentStatusDelta {
+    /// Whether applying this delta would leave a status unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none() && self.changed_metrics.is_empty() && self.removed_metrics.is_empty()
+    }
+
+    /// Merge this delta into the previously received full `status`.
+    pub fn apply_to(&self, status: &mut AgentStatus) {
+        if let Some(runner_status) = &self.status {
+            status.status = runner_status.clone();
+        }
+        for key in &self.removed_metrics {
+            status.metrics.remove(key);
+        }
+        status.metrics.extend(
+            self.changed_metrics
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+    }
+}
+
+/// Build an [`AgentStatus::metrics`] map from `key => value` pairs, where
+/// each value is anything convertible into `serde_json::Value`.
+///
+/// ```
+/// let metrics = evo_common::metrics! { "queue_depth" => 3, "ready" => true };
+/// assert_eq!(metrics["queue_depth"], 3);
+/// ```
+#[macro_export]
+macro_rules! metrics {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        <::std::collections::BTreeMap<::std::string::String, $crate::__private::Value>>::from([
+            $((::std::string::String::from($key), $crate::__private::Value::from($value)),)*
+        ])
+    };
+}
+
+/// Build a [`KingCommand::params`] map; same syntax as [`metrics!`].
+#[macro_export]
+macro_rules! params {
+    ($($tokens:tt)*) => {
+        $crate::metrics!($($tokens)*)
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AgentSkillReport {
     pub agent_id: String,
     pub skill_id: String,
     pub result: SkillResult,
+    /// Quality of this run of the skill, if the agent graded it; averaged
+    /// into [`SkillStats::mean_score`].
+    #[serde(default, deserialize_with = "lenient_score::deserialize")]
     pub score: Option<f64>,
 }
 
+/// Deserializes an optional score from a number, a numeric string
+/// (`"0.85"`), or a percentage string (`"85%"`, read as `0.85`), since LLM
+/// evaluators don't reliably emit numbers. Other strings are an error. Used
+/// for the `AgentSkillReport` and `TaskSummary` scores.
+mod lenient_score {
+    use serde::{Deserialize, Deserializer, de::Error};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawScore {
+        Number(f64),
+        Text(String),
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<f64>, D::Error> {
+        match Option::<RawScore>::deserialize(deserializer)? {
+            None => Ok(None),
+            Some(RawScore::Number(score)) => Ok(Some(score)),
+            Some(RawScore::Text(text)) => parse(&text)
+                .map(Some)
+                .ok_or_else(|| D::Error::custom(format!("invalid score `{text}`"))),
+        }
+    }
+
+    fn parse(text: &str) -> Option<f64> {
+        let text = text.trim();
+        let score = match text.strip_suffix('%') {
+            Some(percent) => percent.trim_end().parse::<f64>().ok()? / 100.0,
+            None => text.parse::<f64>().ok()?,
+        };
+        score.is_finite().then_some(score)
+    }
+}
+
+/// Rollup of the [`AgentSkillReport`]s for one skill.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillStats {
+    pub runs: usize,
+    pub successes: usize,
+    pub failures: usize,
+    pub partials: usize,
+    /// Mean of the reported scores, ignoring reports without one.
+    pub mean_score: Option<f64>,
+    /// Result of the last report in the input slice.
+    pub last_result: SkillResult,
+}
+
+impl SkillStats {
+    /// Fraction of runs that succeeded.
+    pub fn pass_rate(&self) -> f64 {
+        if self.runs == 0 {
+            return 0.0;
+        }
+        self.successes as f64 / self.runs as f64
+    }
+}
+
+/// Per-`skill_id` statistics over `reports`, which are taken to be in
+/// arrival order.
+pub fn skill_stats(reports: &[AgentSkillReport]) -> HashMap<String, SkillStats> {
+    let mut stats: HashMap<String, SkillStats> = HashMap::new();
+    let mut scores: HashMap<&str, (f64, usize)> = HashMap::new();
+    for report in reports {
+        let entry = stats
+            .entry(report.skill_id.clone())
+            .or_insert_with(|| SkillStats {
+                runs: 0,
+                successes: 0,
+                failures: 0,
+                partials: 0,
+                mean_score: None,
+                last_result: report.result.clone(),
+            });
+        entry.runs += 1;
+        match report.result {
+            SkillResult::Success => entry.successes += 1,
+            SkillResult::Failure(_) => entry.failures += 1,
+            SkillResult::Partial(_) => entry.partials += 1,
+        }
+        entry.last_result = report.result.clone();
+        if let Some(score) = report.score {
+            let (sum, count) = scores.entry(&report.skill_id).or_default();
+            *sum += score;
+            *count += 1;
+        }
+    }
+    for (skill_id, (sum, count)) in scores {
+        if let Some(entry) = stats.get_mut(skill_id) {
+            entry.mean_score = Some(sum / count as f64);
+        }
+    }
+    stats
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentHealth {
     pub agent_id: String,
@@ -33,35 +470,217 @@ pub struct AgentHealth {
 pub struct KingCommand {
     pub command: String,
     pub target_agent: String,
-    pub params: HashMap<String, serde_json::Value>,
+    pub params: BTreeMap<String, serde_json::Value>,
+}
+
+/// Agent's acknowledgement of a [`KingCommand`], sent on `king:command_ack`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KingCommandAck {
+    pub request_id: String,
+    pub agent_id: String,
+    pub command: String,
+    pub status: AckStatus,
+    #[serde(default)]
+    pub result: serde_json::Value,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum AckStatus {
+    /// Received and started; a `Completed` or `Failed` ack follows.
+    Accepted,
+    Completed,
+    /// Refused without running (unknown command, bad params, ...).
+    Rejected,
+    Failed,
 }
 
+enum_labels!(AckStatus {
+    Accepted => "accepted",
+    Completed => "completed",
+    Rejected => "rejected",
+    Failed => "failed",
+});
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KingConfigUpdate {
     pub config_type: String,
     pub new_config_hash: String,
 }
 
+/// Build a `KingConfigUpdate` for `config`, hashing it with
+/// [`hash_config`](crate::config::hash_config) so the hash always matches what
+/// consumers recompute.
+pub fn config_update_for<T: Serialize>(
+    config_type: &str,
+    config: &T,
+) -> Result<KingConfigUpdate, serde_json::Error> {
+    Ok(KingConfigUpdate {
+        config_type: config_type.to_string(),
+        new_config_hash: crate::config::hash_config(config)?,
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineNext {
     pub stage: PipelineStage,
     pub artifact_id: String,
-    pub metadata: HashMap<String, serde_json::Value>,
+    pub metadata: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum AgentRole {
+    // "skillmanage" / "preload" are what pre-snake_case agents send.
+    #[serde(alias = "skill-manage", alias = "skillmanage")]
     SkillManage,
     Learning,
+    #[serde(alias = "pre-load", alias = "preload")]
     PreLoad,
     Building,
     Evaluation,
+    /// Deployment-defined role. The name must be non-empty and must not
+    /// shadow a built-in role's label, or its `role:` room would collide
+    /// with the built-in one; construct it with [`AgentRole::user`].
     User(String),
 }
 
+impl AgentRole {
+    /// Custom role named `name`, rejecting empty names and built-in labels
+    /// such as `"learning"`.
+    pub fn user(name: &str) -> Result<AgentRole, RoleError> {
+        if name.is_empty() {
+            return Err(RoleError::Empty);
+        }
+        if AgentRole::builtins().iter().any(|r| r.as_str() == name) {
+            return Err(RoleError::ShadowsBuiltin(name.to_string()));
+        }
+        Ok(AgentRole::User(name.to_string()))
+    }
+
+    pub fn is_user(&self) -> bool {
+        matches!(self, AgentRole::User(_))
+    }
+
+    /// Every built-in role (all variants except `User`).
+    pub fn builtins() -> &'static [AgentRole] {
+        &[
+            AgentRole::SkillManage,
+            AgentRole::Learning,
+            AgentRole::PreLoad,
+            AgentRole::Building,
+            AgentRole::Evaluation,
+        ]
+    }
+
+    /// Snake-case label for built-in roles (matching the serde form), or the
+    /// custom name for `User` roles.
+    pub fn as_str(&self) -> &str {
+        match self {
+            AgentRole::SkillManage => "skill_manage",
+            AgentRole::Learning => "learning",
+            AgentRole::PreLoad => "pre_load",
+            AgentRole::Building => "building",
+            AgentRole::Evaluation => "evaluation",
+            AgentRole::User(name) => name,
+        }
+    }
+}
+
+/// Why [`AgentRole::user`] rejected a custom role name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoleError {
+    Empty,
+    /// The name is a built-in role's label.
+    ShadowsBuiltin(String),
+}
+
+impl fmt::Display for RoleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoleError::Empty => f.write_str("user role name must not be empty"),
+            RoleError::ShadowsBuiltin(name) => {
+                write!(f, "user role `{name}` shadows a built-in role")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RoleError {}
+
+/// Socket.IO room shared by every agent with `role` (e.g. `role:learning`).
+pub fn role_room(role: &AgentRole) -> String {
+    format!("{}{}", events::ROOM_ROLE_PREFIX, role.as_str())
+}
+
+/// What a Socket.IO room name refers to.
+///
+/// Parsing never fails: names without a known prefix become `Unknown`, since
+/// deployments create custom rooms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoomKind {
+    /// The shared `kernel` room.
+    Kernel,
+    /// `role:<role>`; unrecognized role names parse as `AgentRole::User`.
+    Role(AgentRole),
+    /// `task:<task_id>`.
+    Task(String),
+    Unknown(String),
+}
+
+// `TryFrom<&str>` (with `Infallible`) comes from the blanket impl over `From`.
+impl From<&str> for RoomKind {
+    fn from(room: &str) -> Self {
+        if room == events::ROOM_KERNEL {
+            return RoomKind::Kernel;
+        }
+        if let Some(name) = room
+            .strip_prefix(events::ROOM_ROLE_PREFIX)
+            .filter(|n| !n.is_empty())
+        {
+            let role = AgentRole::builtins()
+                .iter()
+                .find(|r| r.as_str() == name)
+                .cloned()
+                .unwrap_or_else(|| AgentRole::User(name.to_string()));
+            return RoomKind::Role(role);
+        }
+        if let Some(task_id) = room
+            .strip_prefix(events::ROOM_TASK_PREFIX)
+            .filter(|id| !id.is_empty())
+        {
+            return RoomKind::Task(task_id.to_string());
+        }
+        RoomKind::Unknown(room.to_string())
+    }
+}
+
+impl FromStr for RoomKind {
+    type Err = std::convert::Infallible;
+
+    fn from_str(room: &str) -> Result<Self, Self::Err> {
+        Ok(RoomKind::from(room))
+    }
+}
+
+impl fmt::Display for RoomKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoomKind::Kernel => f.write_str(events::ROOM_KERNEL),
+            RoomKind::Role(role) => write!(f, "{}{}", events::ROOM_ROLE_PREFIX, role.as_str()),
+            RoomKind::Task(task_id) => write!(f, "{}{task_id}", events::ROOM_TASK_PREFIX),
+            RoomKind::Unknown(room) => f.write_str(room),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum RunnerStatus {
     Starting,
     Ready,
@@ -70,8 +689,65 @@ pub enum RunnerStatus {
     Shutting,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+enum_labels!(RunnerStatus {
+    Starting => "starting",
+    Ready => "ready",
+    Busy => "busy",
+    Error => "error",
+    Shutting => "shutting",
+});
+
+/// The last `capacity` observed runner statuses with their timestamps, for
+/// spotting agents that flap between states.
+#[derive(Debug, Clone)]
+pub struct StatusHistory {
+    capacity: usize,
+    entries: VecDeque<(RunnerStatus, String)>,
+}
+
+impl StatusHistory {
+    pub fn new(capacity: usize) -> Self {
+        StatusHistory {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Record `status` as observed `at` (RFC 3339), dropping the oldest entry
+    /// once `capacity` is reached.
+    pub fn record(&mut self, status: RunnerStatus, at: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((status, at));
+    }
+
+    /// Recorded `(status, at)` pairs, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = (&RunnerStatus, &str)> {
+        self.entries
+            .iter()
+            .map(|(status, at)| (status, at.as_str()))
+    }
+
+    /// Number of status changes among the last `window` entries.
+    pub fn flap_count(&self, window: usize) -> usize {
+        let skip = self.entries.len().saturating_sub(window);
+        let recent: Vec<&RunnerStatus> = self.entries.iter().skip(skip).map(|(s, _)| s).collect();
+        recent.windows(2).filter(|pair| pair[0] != pair[1]).count()
+    }
+
+    /// Whether the whole history holds at least `threshold` status changes.
+    pub fn is_flapping(&self, threshold: usize) -> bool {
+        self.flap_count(self.entries.len()) >= threshold
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum SkillResult {
     Success,
     Failure(String),
@@ -87,41 +763,483 @@ pub struct HealthCheck {
     pub error: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg(feature = "reqwest")]
+impl HealthCheck {
+    /// GET `endpoint` with `client` and report the outcome: `healthy` on a 2xx
+    /// status, otherwise `error` describes the status or transport failure.
+    /// `latency_ms` is set whenever a response arrived.
+    pub async fn probe(name: &str, endpoint: &str, client: &reqwest::Client) -> HealthCheck {
+        let started = std::time::Instant::now();
+        let (healthy, latency_ms, error) = match client.get(endpoint).send().await {
+            Ok(response) => {
+                let latency = started.elapsed().as_millis() as u64;
+                let status = response.status();
+                if status.is_success() {
+                    (true, Some(latency), None)
+                } else {
+                    (false, Some(latency), Some(format!("HTTP {status}")))
+                }
+            }
+            Err(e) => (false, None, Some(e.to_string())),
+        };
+        HealthCheck {
+            name: name.to_string(),
+            endpoint: endpoint.to_string(),
+            healthy,
+            latency_ms,
+            error,
+        }
+    }
+}
+
+impl AgentHealth {
+    /// `true` when every check passed (vacuously true with no checks).
+    pub fn is_healthy(&self) -> bool {
+        self.health_checks.iter().all(|c| c.healthy)
+    }
+
+    /// Names of the checks that failed, in report order.
+    pub fn failing(&self) -> Vec<String> {
+        self.health_checks
+            .iter()
+            .filter(|c| !c.healthy)
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    /// Highest measured latency across all checks, if any were measured.
+    pub fn max_latency_ms(&self) -> Option<u64> {
+        self.health_checks.iter().filter_map(|c| c.latency_ms).max()
+    }
+
+    /// Fold a newer report for the same agent into this one: checks in
+    /// `other` replace same-named checks in place, and unknown names are
+    /// appended. Fails without changing `self` if the agents differ.
+    pub fn merge(&mut self, other: AgentHealth) -> Result<(), ValidationError> {
+        if other.agent_id != self.agent_id {
+            return Err(ValidationError::new(
+                "agent_id",
+                format!(
+                    "cannot merge health of `{}` into `{}`",
+                    other.agent_id, self.agent_id
+                ),
+            ));
+        }
+        for check in other.health_checks {
+            match self.health_checks.iter_mut().find(|c| c.name == check.name) {
+                Some(existing) => *existing = check,
+                None => self.health_checks.push(check),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Latency distribution over a set of health checks, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub p50: u64,
+    pub p95: u64,
+    pub p99: u64,
+    pub max: u64,
+    pub count: usize,
+}
+
+/// Nearest-rank percentiles over the checks that measured a latency; checks
+/// with `latency_ms: None` are ignored. `None` if nothing was measured.
+pub fn latency_percentiles(checks: &[HealthCheck]) -> Option<LatencyStats> {
+    let mut latencies: Vec<u64> = checks.iter().filter_map(|c| c.latency_ms).collect();
+    if latencies.is_empty() {
+        return None;
+    }
+    latencies.sort_unstable();
+    let count = latencies.len();
+    let percentile = |p: usize| latencies[(p * count).div_ceil(100).max(1) - 1];
+    Some(LatencyStats {
+        p50: percentile(50),
+        p95: percentile(95),
+        p99: percentile(99),
+        max: latencies[count - 1],
+        count,
+    })
+}
+
+/// Compact form of [`AgentHealth`] for frequent reporting; agents send the
+/// full report on `agent:health` only occasionally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSummary {
+    pub agent_id: String,
+    pub healthy: bool,
+    #[serde(default)]
+    pub failing: Vec<String>,
+    #[serde(default)]
+    pub max_latency_ms: Option<u64>,
+}
+
+impl From<&AgentHealth> for HealthSummary {
+    fn from(health: &AgentHealth) -> Self {
+        Self {
+            agent_id: health.agent_id.clone(),
+            healthy: health.is_healthy(),
+            failing: health.failing(),
+            max_latency_ms: health.max_latency_ms(),
+        }
+    }
+}
+
+/// Stages order by their position in the pipeline (declaration order).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum PipelineStage {
     Learning,
     Building,
+    #[serde(alias = "pre-load")]
     PreLoad,
     Evaluation,
+    #[serde(alias = "skill-manage")]
     SkillManage,
 }
 
+impl PipelineStage {
+    /// Every stage, in declaration order.
+    pub fn all() -> &'static [PipelineStage] {
+        &[
+            PipelineStage::Learning,
+            PipelineStage::Building,
+            PipelineStage::PreLoad,
+            PipelineStage::Evaluation,
+            PipelineStage::SkillManage,
+        ]
+    }
+
+    /// The stages a pipeline run moves through, in order. `SkillManage`
+    /// manages skills alongside the run rather than being a step in it.
+    pub fn pipeline_order() -> &'static [PipelineStage] {
+        &[
+            PipelineStage::Learning,
+            PipelineStage::Building,
+            PipelineStage::PreLoad,
+            PipelineStage::Evaluation,
+        ]
+    }
+
+    /// Position in the pipeline, starting at 0 for `Learning`. `SkillManage`
+    /// sorts after every pipeline stage.
+    pub fn order(&self) -> u8 {
+        match self {
+            PipelineStage::Learning => 0,
+            PipelineStage::Building => 1,
+            PipelineStage::PreLoad => 2,
+            PipelineStage::Evaluation => 3,
+            PipelineStage::SkillManage => 4,
+        }
+    }
+
+    /// The stage after this one in [`PipelineStage::pipeline_order`];
+    /// `None` after `Evaluation` and for `SkillManage`.
+    pub fn next(&self) -> Option<PipelineStage> {
+        let order = Self::pipeline_order();
+        let index = order.iter().position(|stage| stage == self)?;
+        order.get(index + 1).cloned()
+    }
+}
+
+enum_labels!(PipelineStage {
+    Learning => "learning",
+    Building => "building",
+    PreLoad => "pre_load",
+    Evaluation => "evaluation",
+    SkillManage => "skill_manage",
+});
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum PipelineRunStatus {
     Running,
     Completed,
     Failed,
+    #[serde(alias = "timed-out")]
     TimedOut,
 }
 
+enum_labels!(PipelineRunStatus {
+    Running => "running",
+    Completed => "completed",
+    Failed => "failed",
+    TimedOut => "timed_out",
+});
+
 /// Agent reports completion of a pipeline stage back to king.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PipelineStageResult {
     pub run_id: String,
     pub stage: PipelineStage,
     pub agent_id: String,
     pub status: PipelineRunStatus,
-    pub artifact_id: String,
+    /// Artifact produced by the stage; `None` when nothing was produced.
+    /// Travels as `""` when absent for compatibility with older peers.
+    #[serde(default, with = "empty_string_as_none")]
+    pub artifact_id: Option<String>,
     pub output: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Machine-readable cause for `Failed` / `TimedOut` results; `error`
+    /// carries the human-readable detail. Omitted from the wire when `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_reason: Option<FailureReason>,
+}
+
+/// Why a pipeline stage failed or timed out.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum FailureReason {
+    Timeout,
+    /// A skill, artifact or service the stage needs isn't available.
+    DependencyMissing,
+    /// The agent panicked or exited mid-stage.
+    Crash,
+    /// The stage's input (e.g. the previous stage's artifact) was unusable.
+    InvalidInput,
+    Other,
+}
+
+enum_labels!(FailureReason {
+    Timeout => "timeout",
+    DependencyMissing => "dependency_missing",
+    Crash => "crash",
+    InvalidInput => "invalid_input",
+    Other => "other",
+});
+
+impl PipelineStageResult {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.status == PipelineRunStatus::Completed && self.artifact_id.is_none() {
+            return Err(ValidationError::new(
+                "artifact_id",
+                "completed stages must report the artifact they produced",
+            ));
+        }
+        if matches!(
+            self.status,
+            PipelineRunStatus::Failed | PipelineRunStatus::TimedOut
+        ) && self.failure_reason.is_none()
+        {
+            return Err(ValidationError::new(
+                "failure_reason",
+                format!("`{}` stages must set a failure_reason", self.status),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `output` of a [`PipelineStage::Learning`] result.
+    pub fn learning_output(&self) -> Result<LearningOutput, StageOutputError> {
+        self.typed_output(PipelineStage::Learning)
+    }
+
+    /// `output` of a [`PipelineStage::Building`] result.
+    pub fn building_output(&self) -> Result<BuildingOutput, StageOutputError> {
+        self.typed_output(PipelineStage::Building)
+    }
+
+    /// `output` of a [`PipelineStage::Evaluation`] result.
+    pub fn evaluation_output(&self) -> Result<EvaluationOutput, StageOutputError> {
+        self.typed_output(PipelineStage::Evaluation)
+    }
+
+    fn typed_output<T: serde::de::DeserializeOwned>(
+        &self,
+        expected: PipelineStage,
+    ) -> Result<T, StageOutputError> {
+        if self.stage != expected {
+            return Err(StageOutputError::WrongStage {
+                expected,
+                actual: self.stage.clone(),
+            });
+        }
+        T::deserialize(&self.output).map_err(|source| StageOutputError::Invalid {
+            stage: expected,
+            source,
+        })
+    }
+}
+
+/// State of one pipeline run, rebuilt from its stream of stage results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineRun {
+    pub run_id: String,
+    /// Latest result per stage.
+    pub stages: BTreeMap<PipelineStage, PipelineStageResult>,
+    pub status: PipelineRunStatus,
+}
+
+impl PipelineRun {
+    pub fn new(run_id: impl Into<String>) -> Self {
+        PipelineRun {
+            run_id: run_id.into(),
+            stages: BTreeMap::new(),
+            status: PipelineRunStatus::Running,
+        }
+    }
+
+    /// Record `result` (replacing any earlier result for its stage) and
+    /// recompute `status`: `Failed` if any stage failed, otherwise `TimedOut`
+    /// if any timed out, `Completed` once every stage in
+    /// [`PipelineStage::pipeline_order`] completed, and `Running` until then.
+    pub fn apply(&mut self, result: PipelineStageResult) {
+        self.stages.insert(result.stage.clone(), result);
+        let has = |status: PipelineRunStatus| self.stages.values().any(|r| r.status == status);
+        self.status = if has(PipelineRunStatus::Failed) {
+            PipelineRunStatus::Failed
+        } else if has(PipelineRunStatus::TimedOut) {
+            PipelineRunStatus::TimedOut
+        } else if self.next_stage().is_none() {
+            PipelineRunStatus::Completed
+        } else {
+            PipelineRunStatus::Running
+        };
+    }
+
+    /// First stage in [`PipelineStage::pipeline_order`] without a completed
+    /// result.
+    pub fn next_stage(&self) -> Option<PipelineStage> {
+        PipelineStage::pipeline_order()
+            .iter()
+            .find(|stage| {
+                self.stages
+                    .get(stage)
+                    .is_none_or(|r| r.status != PipelineRunStatus::Completed)
+            })
+            .cloned()
+    }
+}
+
+/// Output of the learning stage: skills worth building next.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LearningOutput {
+    #[serde(default)]
+    pub candidates: Vec<String>,
+}
+
+/// Output of the building stage: where the built skill was written.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildingOutput {
+    pub artifact_path: String,
 }
 
+/// Output of the evaluation stage.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvaluationOutput {
+    pub score: f64,
+    #[serde(default)]
+    pub passed: bool,
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// Why a [`PipelineStageResult`]'s `output` couldn't be read as a stage's
+/// typed output.
+#[derive(Debug)]
+pub enum StageOutputError {
+    /// The result is from a different stage than the one requested.
+    WrongStage {
+        expected: PipelineStage,
+        actual: PipelineStage,
+    },
+    /// The stage matches but `output` doesn't have the expected shape.
+    Invalid {
+        stage: PipelineStage,
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for StageOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StageOutputError::WrongStage { expected, actual } => {
+                write!(f, "expected a `{expected}` stage result, got `{actual}`")
+            }
+            StageOutputError::Invalid { stage, source } => {
+                write!(f, "invalid `{stage}` stage output: {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StageOutputError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StageOutputError::WrongStage { .. } => None,
+            StageOutputError::Invalid { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Maps `Option<String>` to a plain string field where `""` (or `null`, or a
+/// missing field) means `None`.
+mod empty_string_as_none {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(value.as_deref().unwrap_or_default())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<String>, D::Error> {
+        let value = Option::<String>::deserialize(deserializer)?;
+        Ok(value.filter(|s| !s.is_empty()))
+    }
+}
+
+/// Lifecycle state of a task.
+///
+/// Like the other protocol enums in this crate, `TaskStatus` is
+/// `#[non_exhaustive]`: variants may be added in minor releases, so matches
+/// outside this crate need a wildcard arm.
+///
+/// ```
+/// use evo_common::messages::TaskStatus;
+///
+/// fn is_terminal(status: &TaskStatus) -> bool {
+///     match status {
+///         TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Cancelled => true,
+///         _ => false,
+///     }
+/// }
+/// assert!(is_terminal(&TaskStatus::Cancelled));
+/// assert!(!is_terminal(&TaskStatus::InProgress));
+/// ```
+///
+/// Listing every variant without a wildcard does not compile downstream:
+///
+/// ```compile_fail
+/// use evo_common::messages::TaskStatus;
+///
+/// fn label(status: TaskStatus) -> &'static str {
+///     match status {
+///         TaskStatus::Pending => "pending",
+///         TaskStatus::InProgress => "in_progress",
+///         TaskStatus::Completed => "completed",
+///         TaskStatus::Failed => "failed",
+///         TaskStatus::Cancelled => "cancelled",
+///         TaskStatus::Recovering => "recovering",
+///         TaskStatus::Decomposed => "decomposed",
+///     }
+/// }
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum TaskStatus {
     Pending,
+    #[serde(alias = "in-progress")]
     InProgress,
     Completed,
     Failed,
@@ -130,24 +1248,116 @@ pub enum TaskStatus {
     Decomposed,
 }
 
+enum_labels!(TaskStatus {
+    Pending => "pending",
+    InProgress => "in_progress",
+    Completed => "completed",
+    Failed => "failed",
+    Cancelled => "cancelled",
+    Recovering => "recovering",
+    Decomposed => "decomposed",
+});
+
+/// Scheduling priority of a task; variants are ordered lowest to highest.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TaskPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+    Critical,
+}
+
+enum_labels!(TaskPriority {
+    Low => "low",
+    Normal => "normal",
+    High => "high",
+    Critical => "critical",
+});
+
 // ─── Task management messages ────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TaskCreate {
     pub task_type: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub agent_id: Option<String>,
     #[serde(default = "default_empty_object")]
     pub payload: serde_json::Value,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub parent_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    #[serde(default)]
+    pub priority: TaskPriority,
+    /// Unrecognised top-level fields such as a deployment's `tenant`, so a
+    /// relayed task keeps them.
+    #[serde(flatten, default)]
+    pub extensions: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TaskUpdate {
-    pub task_id: String,
-    #[serde(default)]
-    pub status: Option<TaskStatus>,
+impl TaskCreate {
+    /// Borrow a nested payload value by JSON Pointer (e.g. `/config/limit`)
+    /// without cloning the payload.
+    pub fn payload_get(&self, pointer: &str) -> Option<&serde_json::Value> {
+        self.payload.pointer(pointer)
+    }
+}
+
+/// Declarative retry behavior for a task or pipeline stage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+}
+
+/// Delay strategy between retry attempts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Backoff {
+    Fixed {
+        secs: u64,
+    },
+    /// `base_secs * factor^attempt`, capped at `max_secs`.
+    Exponential {
+        base_secs: u64,
+        factor: f64,
+        max_secs: u64,
+    },
+}
+
+impl RetryPolicy {
+    /// Delay to wait before retry number `attempt` (zero-based: `0` is the
+    /// first retry after the initial failure).
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed { secs } => Duration::from_secs(secs),
+            Backoff::Exponential {
+                base_secs,
+                factor,
+                max_secs,
+            } => {
+                let secs = base_secs as f64 * factor.powi(attempt.min(i32::MAX as u32) as i32);
+                // `0 * inf` is NaN, and `max_secs as f64` can round past
+                // what a Duration holds; neither may panic on peer input.
+                let secs = if secs.is_nan() { 0.0 } else { secs };
+                Duration::try_from_secs_f64(secs.clamp(0.0, max_secs as f64))
+                    .unwrap_or(Duration::from_secs(max_secs))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskUpdate {
+    pub task_id: String,
+    #[serde(default)]
+    pub status: Option<TaskStatus>,
     #[serde(default)]
     pub agent_id: Option<String>,
     #[serde(default)]
@@ -156,7 +1366,7 @@ pub struct TaskUpdate {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskGet {
-    pub task_id: String,
+    pub task_id: TaskId,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -173,22 +1383,215 @@ pub struct TaskList {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskDelete {
-    pub task_id: String,
+    pub task_id: TaskId,
+    /// Also delete every descendant (children, their children, ...) in the
+    /// same operation. When `false`, children are left in place with a
+    /// dangling `parent_id`.
+    #[serde(default)]
+    pub cascade: bool,
 }
 
+/// King broadcasts `task:deleted` after a [`TaskDelete`], listing every task
+/// it removed: just `task_id`, or the whole subtree for a cascade.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskDeleted {
+    pub task_id: TaskId,
+    pub deleted_ids: Vec<TaskId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TaskRecord {
     pub id: String,
     pub task_type: String,
     pub status: String,
+    #[serde(default)]
     pub agent_id: String,
     pub payload: serde_json::Value,
     #[serde(default)]
     pub parent_id: String,
+    #[serde(default)]
+    pub priority: TaskPriority,
     pub created_at: String,
     pub updated_at: String,
 }
 
+impl TaskRecord {
+    /// The agent the task is assigned to; `None` while unassigned (king
+    /// stores an empty `agent_id` for those).
+    pub fn assigned_agent(&self) -> Option<&str> {
+        Some(self.agent_id.as_str()).filter(|id| !id.is_empty())
+    }
+
+    /// Time since `updated_at` (RFC 3339) as of `now`; `None` if the timestamp
+    /// doesn't parse. Timestamps ahead of `now` count as zero.
+    pub fn age(&self, now: DateTime<Utc>) -> Option<Duration> {
+        let updated_at = DateTime::parse_from_rfc3339(&self.updated_at).ok()?;
+        Some(
+            (now - updated_at.with_timezone(&Utc))
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Whether the task hasn't been updated for longer than `max_age`.
+    /// Records with an unparseable `updated_at` are never stale.
+    pub fn is_stale(&self, now: DateTime<Utc>, max_age: Duration) -> bool {
+        self.age(now).is_some_and(|age| age > max_age)
+    }
+}
+
+/// In-memory index over a set of [`TaskRecord`]s for answering "children of X"
+/// and "all tasks with status Y" without rescanning the whole list.
+#[derive(Debug, Clone, Default)]
+pub struct TaskIndex {
+    records: Vec<TaskRecord>,
+    by_id: HashMap<String, usize>,
+    by_status: HashMap<String, Vec<usize>>,
+    by_parent: HashMap<String, Vec<usize>>,
+}
+
+impl TaskIndex {
+    pub fn new(records: Vec<TaskRecord>) -> Self {
+        let mut by_id = HashMap::with_capacity(records.len());
+        let mut by_status: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_parent: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, record) in records.iter().enumerate() {
+            by_id.insert(record.id.clone(), idx);
+            by_status
+                .entry(record.status.clone())
+                .or_default()
+                .push(idx);
+            if !record.parent_id.is_empty() {
+                by_parent
+                    .entry(record.parent_id.clone())
+                    .or_default()
+                    .push(idx);
+            }
+        }
+        Self {
+            records,
+            by_id,
+            by_status,
+            by_parent,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TaskRecord> {
+        self.by_id.get(id).map(|&idx| &self.records[idx])
+    }
+
+    pub fn by_status(&self, status: TaskStatus) -> Vec<&TaskRecord> {
+        self.lookup(&self.by_status, status.as_str())
+    }
+
+    pub fn children_of(&self, id: &str) -> Vec<&TaskRecord> {
+        self.lookup(&self.by_parent, id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TaskRecord> {
+        self.records.iter()
+    }
+
+    fn lookup(&self, map: &HashMap<String, Vec<usize>>, key: &str) -> Vec<&TaskRecord> {
+        map.get(key)
+            .map(|ids| ids.iter().map(|&idx| &self.records[idx]).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl From<Vec<TaskRecord>> for TaskIndex {
+    fn from(records: Vec<TaskRecord>) -> Self {
+        Self::new(records)
+    }
+}
+
+/// Priority queue of [`TaskRecord`]s: highest `priority` first, and FIFO by
+/// `created_at` within a priority.
+///
+/// `created_at` is parsed as RFC 3339; records with unparseable timestamps sort
+/// after dated ones of the same priority, in insertion order.
+#[derive(Debug, Default)]
+pub struct TaskQueue {
+    heap: BinaryHeap<QueuedTask>,
+    next_seq: u64,
+}
+
+#[derive(Debug)]
+struct QueuedTask {
+    priority: TaskPriority,
+    created_at: DateTime<Utc>,
+    seq: u64,
+    record: TaskRecord,
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap pops the greatest element, so older tasks (and earlier
+        // insertions) must compare greater.
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.created_at.cmp(&self.created_at))
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedTask {}
+
+impl TaskQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, record: TaskRecord) {
+        let created_at = DateTime::parse_from_rfc3339(&record.created_at)
+            .map(|t| t.with_timezone(&Utc))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.heap.push(QueuedTask {
+            priority: record.priority,
+            created_at,
+            seq: self.next_seq,
+            record,
+        });
+        self.next_seq += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<TaskRecord> {
+        self.heap.pop().map(|queued| queued.record)
+    }
+
+    pub fn peek(&self) -> Option<&TaskRecord> {
+        self.heap.peek().map(|queued| &queued.record)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
 fn default_task_limit() -> u32 {
     50
 }
@@ -197,6 +1600,57 @@ fn default_memory_limit() -> u32 {
     20
 }
 
+/// Longest prefix of `s` that fits in `max_bytes` without splitting a
+/// multi-byte character.
+fn truncate_to_char_boundary(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// `s` without ANSI escape sequences: CSI (`ESC [ ... final`), OSC
+/// (`ESC ] ... BEL` or `ESC ] ... ESC \`), and two-character escapes.
+fn strip_ansi(s: &str) -> Cow<'_, str> {
+    if !s.contains('\x1b') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' {
+                        chars.next_if_eq(&'\\');
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Cow::Owned(out)
+}
+
 fn default_empty_object() -> serde_json::Value {
     serde_json::Value::Object(serde_json::Map::new())
 }
@@ -205,15 +1659,27 @@ fn default_empty_object() -> serde_json::Value {
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum MemoryScope {
     System,
     Agent,
     Pipeline,
     Skill,
+    /// Scoped to a single task; requires `task_id` on store.
+    Task,
 }
 
+enum_labels!(MemoryScope {
+    System => "system",
+    Agent => "agent",
+    Pipeline => "pipeline",
+    Skill => "skill",
+    Task => "task",
+});
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum MemoryCategory {
     Case,
     Pattern,
@@ -223,15 +1689,40 @@ pub enum MemoryCategory {
     Event,
 }
 
+enum_labels!(MemoryCategory {
+    Case => "case",
+    Pattern => "pattern",
+    Fact => "fact",
+    Preference => "preference",
+    Resource => "resource",
+    Event => "event",
+});
+
+impl MemoryCategory {
+    /// Suggested lifetime for memories of this category, for king to expire
+    /// them by; `None` means keep indefinitely. Events go stale within a week,
+    /// everything else persists. [`MemoryStore::ttl_secs`] overrides this.
+    pub fn default_ttl(&self) -> Option<Duration> {
+        match self {
+            MemoryCategory::Event => Some(Duration::from_secs(7 * 24 * 60 * 60)),
+            MemoryCategory::Case
+            | MemoryCategory::Pattern
+            | MemoryCategory::Fact
+            | MemoryCategory::Preference
+            | MemoryCategory::Resource => None,
+        }
+    }
+}
+
 /// A single tier entry (l0/l1/l2) for memory creation/update.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MemoryTierEntry {
     pub tier: String,
     pub content: String,
 }
 
 /// Agent stores a memory into king.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MemoryStore {
     pub scope: MemoryScope,
     pub category: MemoryCategory,
@@ -239,7 +1730,7 @@ pub struct MemoryStore {
     pub key: String,
     #[serde(default = "default_empty_object")]
     pub metadata: serde_json::Value,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tags: Vec<String>,
     #[serde(default)]
     pub agent_id: String,
@@ -249,10 +1740,126 @@ pub struct MemoryStore {
     pub skill_id: String,
     #[serde(default)]
     pub relevance_score: f64,
-    #[serde(default)]
+    /// Older agents send a single tier object instead of an array; both are
+    /// accepted. Serialized as an array, omitted when empty.
+    #[serde(
+        default,
+        deserialize_with = "one_or_many::deserialize",
+        skip_serializing_if = "Vec::is_empty"
+    )]
     pub tiers: Vec<MemoryTierEntry>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub task_id: Option<String>,
+    /// Lifetime in seconds overriding the category's
+    /// [`default_ttl`](MemoryCategory::default_ttl). King enforces expiry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+    /// Unrecognised top-level fields from newer agents; re-emitted when the
+    /// memory is serialized again.
+    #[serde(flatten, default)]
+    pub extensions: HashMap<String, serde_json::Value>,
+}
+
+/// Deserializes a `Vec<T>` from either a JSON array or a single value.
+mod one_or_many {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        Many(Vec<T>),
+        One(T),
+    }
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        Ok(match OneOrMany::deserialize(deserializer)? {
+            OneOrMany::Many(items) => items,
+            OneOrMany::One(item) => vec![item],
+        })
+    }
+}
+
+impl MemoryStore {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.scope == MemoryScope::Task && self.task_id.as_deref().is_none_or(str::is_empty) {
+            return Err(ValidationError::new(
+                "task_id",
+                "task-scoped memories must carry a task_id",
+            ));
+        }
+        Ok(())
+    }
+
+    /// How long king should keep this memory: `ttl_secs` if set, otherwise
+    /// the category's [`default_ttl`](MemoryCategory::default_ttl).
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl_secs
+            .map(Duration::from_secs)
+            .or_else(|| self.category.default_ttl())
+    }
+
+    /// Stable key king uses to detect duplicate memories: a hash of `scope`,
+    /// `category`, and the l0 tier's content (or the first tier's, if there
+    /// is no l0) with case and whitespace normalized.
+    pub fn dedup_key(&self) -> String {
+        let content = self
+            .tiers
+            .iter()
+            .find(|t| t.tier == "l0")
+            .or_else(|| self.tiers.first())
+            .map(|t| {
+                t.content
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase()
+            })
+            .unwrap_or_default();
+        crate::config::hash_config(&serde_json::json!({
+            "scope": self.scope,
+            "category": self.category,
+            "content": content,
+        }))
+        .expect("JSON values always serialize")
+    }
+}
+
+/// Default cap on how many memories one `memory:store_batch` may carry.
+pub const DEFAULT_MEMORY_BATCH_LIMIT: usize = 100;
+
+/// Several memories stored with one `memory:store_batch` event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryStoreBatch {
+    pub memories: Vec<MemoryStore>,
+}
+
+impl MemoryStoreBatch {
+    /// Check the batch holds between 1 and `max_len` memories, each valid on
+    /// its own.
+    pub fn validate(&self, max_len: usize) -> Result<(), ValidationError> {
+        if self.memories.is_empty() {
+            return Err(ValidationError::new("memories", "batch is empty"));
+        }
+        if self.memories.len() > max_len {
+            return Err(ValidationError::new(
+                "memories",
+                format!(
+                    "batch has {} memories, limit is {max_len}",
+                    self.memories.len()
+                ),
+            ));
+        }
+        self.memories.iter().try_for_each(MemoryStore::validate)
+    }
+
+    /// Split into individual stores for per-record processing.
+    pub fn into_stores(self) -> Vec<MemoryStore> {
+        self.memories
+    }
 }
 
 /// Agent queries memories from king.
@@ -273,6 +1880,53 @@ pub struct MemoryQuery {
     pub limit: u32,
 }
 
+impl Default for MemoryQuery {
+    /// Unfiltered empty query with the default limit, matching what an empty
+    /// JSON object deserializes to.
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            scope: None,
+            category: None,
+            agent_id: None,
+            tier: None,
+            task_id: None,
+            limit: default_memory_limit(),
+        }
+    }
+}
+
+impl MemoryQuery {
+    /// Search `task_id`'s task-scoped memories.
+    pub fn for_task(task_id: &str, query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            scope: Some(MemoryScope::Task),
+            task_id: Some(task_id.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Search `agent_id`'s agent-scoped memories.
+    pub fn for_agent(agent_id: &str, query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            scope: Some(MemoryScope::Agent),
+            agent_id: Some(agent_id.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Search memories of one category across all scopes.
+    pub fn by_category(category: MemoryCategory, query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            category: Some(category),
+            ..Self::default()
+        }
+    }
+}
+
 /// A single tier in a returned memory record.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryTierRecord {
@@ -311,6 +1965,88 @@ pub struct MemoryRecord {
     pub updated_at: String,
 }
 
+impl MemoryRecord {
+    /// Borrow a nested metadata value by JSON Pointer without cloning.
+    pub fn metadata_get(&self, pointer: &str) -> Option<&serde_json::Value> {
+        self.metadata.pointer(pointer)
+    }
+
+    /// The entry for `tier` (e.g. `"l1"`), if present.
+    pub fn tier(&self, tier: &str) -> Option<&MemoryTierRecord> {
+        self.tiers.iter().find(|t| t.tier == tier)
+    }
+
+    /// The most detailed tier available: l2, then l1, then l0.
+    pub fn most_detailed(&self) -> Option<&MemoryTierRecord> {
+        ["l2", "l1", "l0"].into_iter().find_map(|t| self.tier(t))
+    }
+
+    /// The shortest tier available, for previews: l0, then l1, then l2.
+    pub fn summary_tier(&self) -> Option<&MemoryTierRecord> {
+        ["l0", "l1", "l2"].into_iter().find_map(|t| self.tier(t))
+    }
+
+    // The comparators below sort best-first and break ties by `id`, so
+    // `sort_by` gives the same order in every view.
+
+    /// Highest `relevance_score` first.
+    pub fn cmp_by_relevance(&self, other: &Self) -> Ordering {
+        other
+            .relevance_score
+            .total_cmp(&self.relevance_score)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+
+    /// Most recently updated first; unparseable `updated_at` values last.
+    pub fn cmp_by_recency(&self, other: &Self) -> Ordering {
+        let updated = |m: &Self| DateTime::parse_from_rfc3339(&m.updated_at).ok();
+        // `None < Some`, so comparing other-to-self puts `None` last.
+        updated(other)
+            .cmp(&updated(self))
+            .then_with(|| self.id.cmp(&other.id))
+    }
+
+    /// Highest `access_count` first.
+    pub fn cmp_by_access(&self, other: &Self) -> Ordering {
+        other
+            .access_count
+            .cmp(&self.access_count)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+/// Which [`MemoryRecord`] comparator to list memories by.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum MemorySort {
+    #[default]
+    Relevance,
+    Recency,
+    Access,
+}
+
+enum_labels!(MemorySort {
+    Relevance => "relevance",
+    Recency => "recency",
+    Access => "access",
+});
+
+impl MemorySort {
+    pub fn compare(&self, a: &MemoryRecord, b: &MemoryRecord) -> Ordering {
+        match self {
+            MemorySort::Relevance => a.cmp_by_relevance(b),
+            MemorySort::Recency => a.cmp_by_recency(b),
+            MemorySort::Access => a.cmp_by_access(b),
+        }
+    }
+
+    /// Sort `memories` in place, best first.
+    pub fn sort(&self, memories: &mut [MemoryRecord]) {
+        memories.sort_by(|a, b| self.compare(a, b));
+    }
+}
+
 /// King returns matching memories to an agent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryResult {
@@ -318,6 +2054,58 @@ pub struct MemoryResult {
     pub count: u32,
 }
 
+impl MemoryResult {
+    /// Wrap `memories`, setting `count` to match (saturating at `u32::MAX`).
+    pub fn new(memories: Vec<MemoryRecord>) -> Self {
+        let count = u32::try_from(memories.len()).unwrap_or(u32::MAX);
+        Self { memories, count }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MemoryRecord> {
+        self.memories.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.memories.is_empty()
+    }
+
+    /// The top-ranked memory, if any.
+    pub fn first(&self) -> Option<&MemoryRecord> {
+        self.memories.first()
+    }
+
+    /// Sort `memories` best-first by
+    /// `relevance_weight * relevance_score + popularity_weight * popularity`,
+    /// where popularity is `access_count` min-max normalized to `0.0..=1.0`
+    /// across this result (all zero when every count is equal). Ties keep
+    /// their existing order.
+    pub fn rerank(&mut self, relevance_weight: f64, popularity_weight: f64) {
+        let counts = self.memories.iter().map(|m| m.access_count);
+        let min = counts.clone().min().unwrap_or_default();
+        let max = counts.max().unwrap_or_default();
+        let spread = (max - min) as f64;
+        let rank = |m: &MemoryRecord| {
+            let popularity = if spread > 0.0 {
+                (m.access_count - min) as f64 / spread
+            } else {
+                0.0
+            };
+            relevance_weight * m.relevance_score + popularity_weight * popularity
+        };
+        self.memories.sort_by(|a, b| rank(b).total_cmp(&rank(a)));
+        self.count = self.memories.len() as u32;
+    }
+}
+
+impl IntoIterator for MemoryResult {
+    type Item = MemoryRecord;
+    type IntoIter = std::vec::IntoIter<MemoryRecord>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.memories.into_iter()
+    }
+}
+
 /// Broadcast when a memory is created, updated, or deleted.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MemoryChanged {
@@ -339,8 +2127,47 @@ pub struct TaskInvite {
     pub payload: serde_json::Value,
 }
 
+/// An agent's answer to a [`TaskInvite`], from [`should_accept`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceptDecision {
+    Accept,
+    Decline(String),
+    /// Not now, ask again later. [`should_accept`] itself never defers;
+    /// agents with their own scheduling policy can.
+    Defer,
+}
+
+/// Decide whether an agent with `role` and `status` that handles
+/// `handled_types` should join `invite`'s task room.
+///
+/// Declines when the invite's payload names a different `"role"`, when
+/// `task_type` isn't in `handled_types`, or when `status` isn't `Ready`
+/// (including `Starting`); otherwise accepts.
+pub fn should_accept(
+    invite: &TaskInvite,
+    role: &AgentRole,
+    status: RunnerStatus,
+    handled_types: &[String],
+) -> AcceptDecision {
+    if let Some(wanted) = invite.payload.get("role").and_then(|r| r.as_str())
+        && wanted != role.as_str()
+    {
+        return AcceptDecision::Decline(format!(
+            "task wants role `{wanted}`, agent is `{}`",
+            role.as_str()
+        ));
+    }
+    if !handled_types.contains(&invite.task_type) {
+        return AcceptDecision::Decline(format!("task type `{}` is not handled", invite.task_type));
+    }
+    match status {
+        RunnerStatus::Ready => AcceptDecision::Accept,
+        other => AcceptDecision::Decline(format!("agent is {other}")),
+    }
+}
+
 /// King streams output data into a task room.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TaskOutput {
     pub task_id: String,
     pub request_id: String,
@@ -350,6 +2177,134 @@ pub struct TaskOutput {
     pub chunk_index: u32,
     #[serde(default)]
     pub is_final: bool,
+    /// How `delta` is encoded; see [`TaskOutput::decoded_text`].
+    #[serde(default)]
+    pub encoding: OutputEncoding,
+}
+
+/// Encoding of a [`TaskOutput::delta`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum OutputEncoding {
+    /// Plain text.
+    #[default]
+    Utf8,
+    /// Raw terminal output that may contain ANSI escape sequences.
+    Ansi,
+    /// Standard base64 of binary output.
+    Base64,
+}
+
+enum_labels!(OutputEncoding {
+    Utf8 => "utf8",
+    Ansi => "ansi",
+    Base64 => "base64",
+});
+
+impl TaskOutput {
+    /// `delta` as displayable text: ANSI escapes stripped for `Ansi`, and
+    /// decoded (invalid UTF-8 replaced) for `Base64`. A `Base64` delta that
+    /// isn't valid base64 is returned unchanged.
+    pub fn decoded_text(&self) -> Cow<'_, str> {
+        use base64::Engine;
+
+        match self.encoding {
+            OutputEncoding::Utf8 => Cow::Borrowed(&self.delta),
+            OutputEncoding::Ansi => strip_ansi(&self.delta),
+            OutputEncoding::Base64 => {
+                match base64::engine::general_purpose::STANDARD.decode(&self.delta) {
+                    Ok(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+                    Err(_) => Cow::Borrowed(&self.delta),
+                }
+            }
+        }
+    }
+
+    /// `delta` cut to at most `max_bytes`, backing off to the previous char
+    /// boundary so multi-byte characters are never split.
+    ///
+    /// The cut is on the encoded text: a `Base64` delta cut inside a
+    /// 4-character group no longer decodes, so [`decoded_text`] returns it
+    /// raw, and an `Ansi` delta can lose the tail of an escape sequence.
+    ///
+    /// [`decoded_text`]: TaskOutput::decoded_text
+    pub fn truncated_delta(&self, max_bytes: usize) -> &str {
+        truncate_to_char_boundary(&self.delta, max_bytes)
+    }
+
+    /// In-place version of [`TaskOutput::truncated_delta`].
+    pub fn truncate_delta(&mut self, max_bytes: usize) {
+        let len = self.truncated_delta(max_bytes).len();
+        self.delta.truncate(len);
+    }
+}
+
+// ─── Stream reassembly ──────────────────────────────────────────────────────
+
+/// LLM delta streamed on `debug:stream` for live debugging of a request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugStream {
+    pub request_id: String,
+    pub delta: String,
+    pub chunk_index: u32,
+    #[serde(default)]
+    pub is_final: bool,
+}
+
+/// Outcome of pushing one chunk into a stream assembler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssembleState {
+    /// Chunk accepted; more are expected.
+    Pending,
+    /// Final chunk accepted; carries the joined text of the whole stream.
+    Complete(String),
+    /// Chunk arrived out of order. The partial stream is discarded.
+    OutOfOrder { expected: u32, got: u32 },
+}
+
+/// Reassembles `debug:stream` chunks per `request_id`, checking that
+/// `chunk_index` counts up from zero without gaps.
+#[derive(Debug, Default)]
+pub struct DebugStreamAssembler {
+    streams: HashMap<String, PartialStream>,
+}
+
+#[derive(Debug, Default)]
+struct PartialStream {
+    next_index: u32,
+    text: String,
+}
+
+impl DebugStreamAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, ev: &DebugStream) -> AssembleState {
+        let stream = self.streams.entry(ev.request_id.clone()).or_default();
+        if ev.chunk_index != stream.next_index {
+            let expected = stream.next_index;
+            self.streams.remove(&ev.request_id);
+            return AssembleState::OutOfOrder {
+                expected,
+                got: ev.chunk_index,
+            };
+        }
+        stream.text.push_str(&ev.delta);
+        stream.next_index += 1;
+        if ev.is_final {
+            let stream = self.streams.remove(&ev.request_id).unwrap_or_default();
+            AssembleState::Complete(stream.text)
+        } else {
+            AssembleState::Pending
+        }
+    }
+
+    /// Number of streams still waiting for their final chunk.
+    pub fn pending(&self) -> usize {
+        self.streams.len()
+    }
 }
 
 /// King requests evaluation of a completed task.
@@ -368,13 +2323,57 @@ pub struct TaskEvaluate {
     pub metadata: serde_json::Value,
 }
 
+/// Default cap, in bytes, on `TaskEvaluate.output_summary`.
+pub const DEFAULT_OUTPUT_LIMIT: usize = 64 * 1024;
+
+impl TaskEvaluate {
+    /// Assemble an evaluation request from a task's streamed output: each
+    /// delta's [`decoded_text`](TaskOutput::decoded_text) is joined in
+    /// `chunk_index` order (regardless of arrival order) and truncated to
+    /// [`DEFAULT_OUTPUT_LIMIT`] bytes on a char boundary.
+    pub fn from_outputs(
+        task_id: &str,
+        task_type: &str,
+        outputs: &[TaskOutput],
+        exit_code: Option<i32>,
+        latency_ms: Option<u64>,
+    ) -> TaskEvaluate {
+        let mut ordered: Vec<&TaskOutput> = outputs.iter().collect();
+        ordered.sort_by_key(|o| o.chunk_index);
+        let mut summary = String::new();
+        for output in ordered {
+            if summary.len() >= DEFAULT_OUTPUT_LIMIT {
+                break;
+            }
+            summary.push_str(&output.decoded_text());
+        }
+        let end = truncate_to_char_boundary(&summary, DEFAULT_OUTPUT_LIMIT).len();
+        summary.truncate(end);
+
+        TaskEvaluate {
+            task_id: task_id.to_string(),
+            task_type: task_type.to_string(),
+            output_summary: summary,
+            exit_code,
+            latency_ms,
+            metadata: default_empty_object(),
+        }
+    }
+
+    /// Borrow a nested metadata value by JSON Pointer without cloning.
+    pub fn metadata_get(&self, pointer: &str) -> Option<&serde_json::Value> {
+        self.metadata.pointer(pointer)
+    }
+}
+
 /// Evaluation agent reports a task summary.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TaskSummary {
     pub task_id: String,
     pub agent_id: String,
     pub summary: String,
-    #[serde(default)]
+    /// The evaluator's grade for the task's output, `None` if it gave none.
+    #[serde(default, deserialize_with = "lenient_score::deserialize")]
     pub score: Option<f64>,
     #[serde(default)]
     pub tags: Vec<String>,
@@ -382,11 +2381,87 @@ pub struct TaskSummary {
     pub evaluation: serde_json::Value,
 }
 
+/// The conventional sections of [`TaskSummary::evaluation`]. Missing sections
+/// are empty; keys beyond these are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvaluationDetail {
+    pub strengths: Vec<String>,
+    pub weaknesses: Vec<String>,
+    pub rubric_scores: HashMap<String, f64>,
+}
+
+impl TaskSummary {
+    /// Typed view of `evaluation`. An absent (`null`) evaluation yields an
+    /// empty [`EvaluationDetail`]; sections of the wrong shape are an error.
+    pub fn evaluation_detail(&self) -> Result<EvaluationDetail, serde_json::Error> {
+        if self.evaluation.is_null() {
+            return Ok(EvaluationDetail::default());
+        }
+        EvaluationDetail::deserialize(&self.evaluation)
+    }
+}
+
+/// Combine several evaluators' summaries of the same task into one.
+///
+/// Scores are averaged (ignoring `None`), tags are unioned in first-seen
+/// order, summary texts are joined one per line prefixed with their agent,
+/// and `evaluation` objects are merged shallowly with later keys winning.
+/// The merged `agent_id` lists the contributing agents, comma-separated.
+pub fn merge_summaries(summaries: &[TaskSummary]) -> Result<TaskSummary, ValidationError> {
+    let Some(first) = summaries.first() else {
+        return Err(ValidationError::new("summaries", "nothing to merge"));
+    };
+    if let Some(other) = summaries.iter().find(|s| s.task_id != first.task_id) {
+        return Err(ValidationError::new(
+            "task_id",
+            format!(
+                "summaries are for different tasks (`{}` and `{}`)",
+                first.task_id, other.task_id
+            ),
+        ));
+    }
+
+    let scores: Vec<f64> = summaries.iter().filter_map(|s| s.score).collect();
+    let score = (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
+
+    let mut agents: Vec<&str> = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    let mut evaluation = serde_json::Map::new();
+    for summary in summaries {
+        if !agents.contains(&summary.agent_id.as_str()) {
+            agents.push(&summary.agent_id);
+        }
+        for tag in &summary.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        if let Some(fields) = summary.evaluation.as_object() {
+            evaluation.extend(fields.clone());
+        }
+    }
+
+    Ok(TaskSummary {
+        task_id: first.task_id.clone(),
+        agent_id: agents.join(","),
+        summary: summaries
+            .iter()
+            .map(|s| format!("[{}] {}", s.agent_id, s.summary))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        score,
+        tags,
+        evaluation: serde_json::Value::Object(evaluation),
+    })
+}
+
 // ─── Error recovery & task decomposition ─────────────────────────────────────
 
 /// Recommendation from evaluation agent on how to handle a pipeline failure.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum ErrorRecoveryAction {
     Retry,
     Decompose,
@@ -465,33 +2540,451 @@ pub struct TaskDecomposeResponse {
     pub subtasks: Vec<TaskSubtaskSpec>,
 }
 
-pub mod events {
-    pub const AGENT_REGISTER: &str = "agent:register";
-    pub const AGENT_STATUS: &str = "agent:status";
-    pub const AGENT_SKILL_REPORT: &str = "agent:skill_report";
-    pub const AGENT_HEALTH: &str = "agent:health";
-    pub const KING_COMMAND: &str = "king:command";
-    pub const KING_CONFIG_UPDATE: &str = "king:config_update";
-    pub const PIPELINE_NEXT: &str = "pipeline:next";
+// ─── Event routing ───────────────────────────────────────────────────────────
 
-    // Task management events
-    pub const TASK_CREATE: &str = "task:create";
-    pub const TASK_UPDATE: &str = "task:update";
-    pub const TASK_GET: &str = "task:get";
-    pub const TASK_LIST: &str = "task:list";
-    pub const TASK_DELETE: &str = "task:delete";
-    pub const TASK_CHANGED: &str = "task:changed";
+/// Declares [`Message`] with one variant per typed event payload, plus the
+/// event-name routing in both directions.
+macro_rules! messages {
+    ($($variant:ident => $event:ident),+ $(,)?) => {
+        /// Any typed Socket.IO payload, tagged by the event it travels on.
+        ///
+        /// Serializes as the bare payload (untagged); use
+        /// [`Message::event_name`] for the event to emit it on.
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(untagged)]
+        #[non_exhaustive]
+        pub enum Message {
+            $($variant($variant),)+
+        }
 
-    // Pipeline coordination events
-    pub const PIPELINE_STAGE_RESULT: &str = "pipeline:stage_result";
+        impl Message {
+            /// Deserialize `payload` into the variant matching `event`.
+            pub fn from_event(event: &str, payload: serde_json::Value) -> Result<Message, MessageError> {
+                let parsed = match event {
+                    $(events::$event => serde_json::from_value(payload).map(Message::$variant),)+
+                    _ => return Err(MessageError::UnknownEvent(event.to_string())),
+                };
+                parsed.map_err(|source| MessageError::InvalidPayload {
+                    event: event.to_string(),
+                    source,
+                })
+            }
 
-    // Debug events
-    pub const DEBUG_PROMPT: &str = "debug:prompt";
-    pub const DEBUG_RESPONSE: &str = "debug:response";
+            pub fn event_name(&self) -> &'static str {
+                match self {
+                    $(Message::$variant(_) => events::$event,)+
+                }
+            }
+        }
+    };
+}
+
+messages! {
+    AgentRegister => AGENT_REGISTER,
+    AgentStatus => AGENT_STATUS,
+    AgentStatusDelta => AGENT_STATUS_DELTA,
+    AgentSkillReport => AGENT_SKILL_REPORT,
+    AgentHealth => AGENT_HEALTH,
+    HealthSummary => AGENT_HEALTH_SUMMARY,
+    KingCommand => KING_COMMAND,
+    KingCommandAck => KING_COMMAND_ACK,
+    KingConfigUpdate => KING_CONFIG_UPDATE,
+    PipelineNext => PIPELINE_NEXT,
+    PipelineStageResult => PIPELINE_STAGE_RESULT,
+    TaskCreate => TASK_CREATE,
+    TaskUpdate => TASK_UPDATE,
+    TaskGet => TASK_GET,
+    TaskList => TASK_LIST,
+    TaskDelete => TASK_DELETE,
+    TaskDeleted => TASK_DELETED,
+    DebugStream => DEBUG_STREAM,
+    MemoryStore => MEMORY_STORE,
+    MemoryStoreBatch => MEMORY_STORE_BATCH,
+    MemoryQuery => MEMORY_QUERY,
+    MemoryChanged => MEMORY_CHANGED,
+    TaskInvite => TASK_INVITE,
+    TaskOutput => TASK_OUTPUT,
+    TaskEvaluate => TASK_EVALUATE,
+    TaskSummary => TASK_SUMMARY,
+    ErrorRecoveryRequest => ERROR_RECOVERY_REQUEST,
+    ErrorRecoveryResponse => ERROR_RECOVERY_RESPONSE,
+    TaskDecomposeRequest => TASK_DECOMPOSE,
+    TaskDecomposeResponse => TASK_DECOMPOSE_RESULT,
+}
+
+/// Why an incoming event couldn't be turned into a [`Message`].
+#[derive(Debug)]
+pub enum MessageError {
+    /// The event name has no typed payload in this crate.
+    UnknownEvent(String),
+    /// The payload doesn't match the struct for its event.
+    InvalidPayload {
+        event: String,
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for MessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageError::UnknownEvent(event) => write!(f, "unknown event `{event}`"),
+            MessageError::InvalidPayload { event, source } => {
+                write!(f, "invalid `{event}` payload: {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MessageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MessageError::UnknownEvent(_) => None,
+            MessageError::InvalidPayload { source, .. } => Some(source),
+        }
+    }
+}
+
+// ─── Golden samples ──────────────────────────────────────────────────────────
+
+/// One canonical payload per covered event, as `(event, json)`.
+///
+/// These pin the wire format that non-Rust agents rely on: the test suite
+/// compares each against a checked-in JSON string, so any field rename,
+/// removal or encoding change fails loudly.
+pub fn golden_samples() -> Vec<(&'static str, serde_json::Value)> {
+    let samples = [
+        Message::AgentRegister(AgentRegister {
+            agent_id: "learning-001".into(),
+            role: AgentRole::Learning,
+            capabilities: vec!["web-search".into()],
+            protocol_version: Some(PROTOCOL_VERSION),
+        }),
+        Message::TaskCreate(TaskCreate {
+            task_type: "summarize".into(),
+            agent_id: Some("learning-001".into()),
+            payload: serde_json::json!({ "url": "https://example.com" }),
+            parent_id: None,
+            retry_policy: Some(RetryPolicy {
+                max_attempts: 3,
+                backoff: Backoff::Fixed { secs: 5 },
+            }),
+            priority: TaskPriority::High,
+            extensions: HashMap::new(),
+        }),
+        Message::MemoryStore(MemoryStore {
+            scope: MemoryScope::Task,
+            category: MemoryCategory::Case,
+            key: "retry-batch".into(),
+            metadata: serde_json::json!({ "source": "pty" }),
+            tags: vec!["retry".into()],
+            agent_id: "learning-001".into(),
+            run_id: "run-001".into(),
+            skill_id: String::new(),
+            relevance_score: 0.5,
+            tiers: vec![MemoryTierEntry {
+                tier: "l0".into(),
+                content: "retry with smaller batch".into(),
+            }],
+            task_id: Some("task-001".into()),
+            ttl_secs: None,
+            extensions: HashMap::new(),
+        }),
+        Message::PipelineStageResult(PipelineStageResult {
+            run_id: "run-001".into(),
+            stage: PipelineStage::Building,
+            agent_id: "building-001".into(),
+            status: PipelineRunStatus::Completed,
+            artifact_id: Some("artifact-001".into()),
+            output: serde_json::json!({ "artifact_path": "/skills/web-search" }),
+            error: None,
+            failure_reason: None,
+        }),
+    ];
+    samples
+        .into_iter()
+        .map(|message| {
+            let json = serde_json::to_value(&message).expect("sample payloads serialize");
+            (message.event_name(), json)
+        })
+        .collect()
+}
+
+// ─── Envelope ────────────────────────────────────────────────────────────────
+
+/// A Socket.IO event name that is cheap to clone.
+///
+/// Names from [`events`] are held as `&'static str`; anything else is shared
+/// behind an `Arc<str>`. Serializes as the plain string.
+#[derive(Clone)]
+pub struct EventName(EventNameRepr);
+
+#[derive(Clone)]
+enum EventNameRepr {
+    Static(&'static str),
+    Shared(Arc<str>),
+}
+
+impl EventName {
+    pub const fn from_static(name: &'static str) -> Self {
+        EventName(EventNameRepr::Static(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            EventNameRepr::Static(name) => name,
+            EventNameRepr::Shared(name) => name,
+        }
+    }
+
+    /// Whether this name is held without an allocation.
+    pub fn is_static(&self) -> bool {
+        matches!(self.0, EventNameRepr::Static(_))
+    }
+}
+
+impl From<&'static str> for EventName {
+    fn from(name: &'static str) -> Self {
+        EventName::from_static(name)
+    }
+}
+
+impl From<String> for EventName {
+    /// Known event names are interned to their constant.
+    fn from(name: String) -> Self {
+        match events::ALL.iter().find(|known| **known == name) {
+            Some(known) => EventName::from_static(known),
+            None => EventName(EventNameRepr::Shared(name.into())),
+        }
+    }
+}
+
+impl PartialEq for EventName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for EventName {}
+
+impl PartialEq<str> for EventName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for EventName {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl std::hash::Hash for EventName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl AsRef<str> for EventName {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for EventName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for EventName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for EventName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for EventName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(EventName::from)
+    }
+}
+
+/// A payload with the event it travels on and the sender's trace context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub event: EventName,
+    /// Unique per emitted envelope (a UUID v4), so receivers can drop
+    /// redeliveries; see [`Envelope::is_duplicate`]. Empty from senders that
+    /// predate it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message_id: String,
+    /// When the envelope was created (RFC 3339, UTC); empty from senders
+    /// that predate it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub emitted_at: String,
+    /// W3C trace-context carrier (see `tracing_context::inject_context`).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub trace: HashMap<String, String>,
+    pub payload: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wrap `payload` with a fresh `message_id` and the current time.
+    pub fn new(event: impl Into<EventName>, payload: T) -> Self {
+        Envelope {
+            event: event.into(),
+            message_id: uuid::Uuid::new_v4().to_string(),
+            emitted_at: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            trace: HashMap::new(),
+            payload,
+        }
+    }
+
+    /// Record `message_id` in `cache`, returning `true` if it was already
+    /// there, i.e. this envelope is a redelivery and should be skipped.
+    /// Envelopes without a `message_id` are never considered duplicates.
+    pub fn is_duplicate(&self, cache: &mut DedupCache) -> bool {
+        !self.message_id.is_empty() && cache.observe(&self.message_id)
+    }
+
+    /// Inject the current `tracing` span's trace context into `trace`.
+    /// Without the `tracing-otel` feature this leaves `trace` untouched.
+    #[cfg_attr(not(feature = "tracing-otel"), allow(unused_mut))]
+    pub fn with_current_trace(mut self) -> Self {
+        #[cfg(feature = "tracing-otel")]
+        crate::tracing_context::inject_span_context(&tracing::Span::current(), &mut self.trace);
+        self
+    }
+}
+
+/// Wrap `payload` in an [`Envelope`] for `event`, inject the current trace
+/// context, and pass the envelope to `emit` (any callable), returning its
+/// result.
+///
+/// ```
+/// use evo_common::messages::{Envelope, TaskGet, events};
+///
+/// let mut sent = Vec::new();
+/// evo_common::emit_event!(
+///     |envelope: Envelope<TaskGet>| sent.push(envelope),
+///     events::TASK_GET,
+///     TaskGet { task_id: "t1".into() }
+/// );
+/// assert_eq!(sent[0].event, events::TASK_GET);
+/// ```
+#[macro_export]
+macro_rules! emit_event {
+    ($emit:expr, $event:expr, $payload:expr $(,)?) => {
+        ($emit)($crate::messages::Envelope::new($event, $payload).with_current_trace())
+    };
+}
+
+impl From<Message> for Envelope<Message> {
+    fn from(message: Message) -> Self {
+        Envelope::new(message.event_name(), message)
+    }
+}
+
+/// The `capacity` most recently seen envelope `message_id`s, for skipping
+/// events that at-least-once delivery hands over twice. Seeing an id again
+/// makes it the most recent; the least recently seen id is evicted once
+/// `capacity` is exceeded.
+#[derive(Debug, Clone)]
+pub struct DedupCache {
+    capacity: usize,
+    tick: u64,
+    last_seen: HashMap<String, u64>,
+    // `(id, tick)` in observation order; entries whose tick no longer
+    // matches `last_seen` are stale and skipped on eviction.
+    order: VecDeque<(String, u64)>,
+}
+
+impl DedupCache {
+    pub fn new(capacity: usize) -> Self {
+        DedupCache {
+            capacity,
+            tick: 0,
+            last_seen: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Mark `id` as seen, returning whether it already was. With a capacity
+    /// of zero nothing is remembered.
+    pub fn observe(&mut self, id: &str) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+        self.tick += 1;
+        let seen = self.last_seen.insert(id.to_string(), self.tick).is_some();
+        self.order.push_back((id.to_string(), self.tick));
+        while self.last_seen.len() > self.capacity {
+            let Some((oldest, tick)) = self.order.pop_front() else {
+                break;
+            };
+            if self.last_seen.get(&oldest) == Some(&tick) {
+                self.last_seen.remove(&oldest);
+            }
+        }
+        if self.order.len() > 2 * self.capacity {
+            let last_seen = &self.last_seen;
+            self.order
+                .retain(|(id, tick)| last_seen.get(id) == Some(tick));
+        }
+        seen
+    }
+
+    /// Whether `id` is currently remembered, without refreshing it.
+    pub fn contains(&self, id: &str) -> bool {
+        self.last_seen.contains_key(id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last_seen.is_empty()
+    }
+}
+
+pub mod events {
+    pub const AGENT_REGISTER: &str = "agent:register";
+    pub const AGENT_STATUS: &str = "agent:status";
+    pub const AGENT_STATUS_DELTA: &str = "agent:status_delta";
+    pub const AGENT_SKILL_REPORT: &str = "agent:skill_report";
+    pub const AGENT_HEALTH: &str = "agent:health";
+    pub const AGENT_HEALTH_SUMMARY: &str = "agent:health_summary";
+    pub const KING_COMMAND: &str = "king:command";
+    pub const KING_COMMAND_ACK: &str = "king:command_ack";
+    pub const KING_CONFIG_UPDATE: &str = "king:config_update";
+    pub const PIPELINE_NEXT: &str = "pipeline:next";
+
+    // Task management events
+    pub const TASK_CREATE: &str = "task:create";
+    pub const TASK_UPDATE: &str = "task:update";
+    pub const TASK_GET: &str = "task:get";
+    pub const TASK_LIST: &str = "task:list";
+    pub const TASK_DELETE: &str = "task:delete";
+    pub const TASK_DELETED: &str = "task:deleted";
+    pub const TASK_CHANGED: &str = "task:changed";
+
+    // Pipeline coordination events
+    pub const PIPELINE_STAGE_RESULT: &str = "pipeline:stage_result";
+
+    // Debug events
+    pub const DEBUG_PROMPT: &str = "debug:prompt";
+    pub const DEBUG_RESPONSE: &str = "debug:response";
     pub const DEBUG_STREAM: &str = "debug:stream";
 
     // Memory events
     pub const MEMORY_STORE: &str = "memory:store";
+    pub const MEMORY_STORE_BATCH: &str = "memory:store_batch";
     pub const MEMORY_QUERY: &str = "memory:query";
     pub const MEMORY_UPDATE: &str = "memory:update";
     pub const MEMORY_DELETE: &str = "memory:delete";
@@ -516,6 +3009,89 @@ pub mod events {
     // System info events
     pub const KING_SYSTEM_INFO: &str = "king:system_info";
 
+    /// Every event name above (rooms excluded). Also used to intern incoming
+    /// names as [`EventName`](super::EventName)s without allocating.
+    pub const ALL: &[&str] = &[
+        AGENT_REGISTER,
+        AGENT_STATUS,
+        AGENT_STATUS_DELTA,
+        AGENT_SKILL_REPORT,
+        AGENT_HEALTH,
+        AGENT_HEALTH_SUMMARY,
+        KING_COMMAND,
+        KING_COMMAND_ACK,
+        KING_CONFIG_UPDATE,
+        PIPELINE_NEXT,
+        TASK_CREATE,
+        TASK_UPDATE,
+        TASK_GET,
+        TASK_LIST,
+        TASK_DELETE,
+        TASK_DELETED,
+        TASK_CHANGED,
+        PIPELINE_STAGE_RESULT,
+        DEBUG_PROMPT,
+        DEBUG_RESPONSE,
+        DEBUG_STREAM,
+        MEMORY_STORE,
+        MEMORY_STORE_BATCH,
+        MEMORY_QUERY,
+        MEMORY_UPDATE,
+        MEMORY_DELETE,
+        MEMORY_CHANGED,
+        TASK_INVITE,
+        TASK_JOIN,
+        TASK_OUTPUT,
+        TASK_EVALUATE,
+        TASK_SUMMARY,
+        TASK_LOG,
+        ERROR_RECOVERY_REQUEST,
+        ERROR_RECOVERY_RESPONSE,
+        TASK_DECOMPOSE,
+        TASK_DECOMPOSE_RESULT,
+        KING_SYSTEM_INFO,
+    ];
+
+    /// Whether `name` is one of the event names in [`ALL`].
+    pub fn is_known_event(name: &str) -> bool {
+        ALL.contains(&name)
+    }
+
+    /// Payload size limit for events without a specific one in
+    /// [`max_payload_bytes`], including unknown events.
+    pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 256 * 1024;
+
+    /// Largest serialized payload, in bytes, accepted for `event`:
+    ///
+    /// - 16 KiB for periodic status/health reports and id-only requests
+    ///   (`agent:status`, `agent:status_delta`, `agent:health`,
+    ///   `agent:health_summary`, `agent:register`, `king:command_ack`,
+    ///   `task:get`, `task:delete`, `task:join`, `memory:delete`), which
+    ///   should stay small;
+    /// - 64 KiB for streamed chunks (`task:output`, `debug:stream`);
+    /// - 4 MiB for results that carry full outputs or many records
+    ///   (`task:evaluate`, `task:summary`, `pipeline:stage_result`,
+    ///   `task:decompose_result`, `memory:store_batch`, `debug:prompt`,
+    ///   `debug:response`);
+    /// - [`DEFAULT_MAX_PAYLOAD_BYTES`] (256 KiB) for everything else.
+    pub fn max_payload_bytes(event: &str) -> usize {
+        const KIB: usize = 1024;
+        match event {
+            AGENT_STATUS | AGENT_STATUS_DELTA | AGENT_HEALTH | AGENT_HEALTH_SUMMARY
+            | AGENT_REGISTER | KING_COMMAND_ACK | TASK_GET | TASK_DELETE | TASK_JOIN
+            | MEMORY_DELETE => 16 * KIB,
+            TASK_OUTPUT | DEBUG_STREAM => 64 * KIB,
+            TASK_EVALUATE
+            | TASK_SUMMARY
+            | PIPELINE_STAGE_RESULT
+            | TASK_DECOMPOSE_RESULT
+            | MEMORY_STORE_BATCH
+            | DEBUG_PROMPT
+            | DEBUG_RESPONSE => 4 * KIB * KIB,
+            _ => DEFAULT_MAX_PAYLOAD_BYTES,
+        }
+    }
+
     // Rooms
     pub const ROOM_KERNEL: &str = "kernel";
     pub const ROOM_ROLE_PREFIX: &str = "role:";
@@ -526,12 +3102,70 @@ pub mod events {
 mod tests {
     use super::*;
 
+    #[test]
+    fn ids_serialize_as_plain_strings() {
+        let json = serde_json::to_string(&AgentId::from("learning-001")).unwrap();
+        assert_eq!(json, r#""learning-001""#);
+        let id: TaskId = serde_json::from_str(r#""task-001""#).unwrap();
+        assert_eq!(id.as_str(), "task-001");
+        assert_eq!(RunId::new("run-001").to_string(), "run-001");
+        assert_eq!(
+            String::from(SkillId::from("web-search".to_string())),
+            "web-search"
+        );
+    }
+
+    #[test]
+    fn migrated_messages_keep_wire_format() {
+        let msg = TaskGet {
+            task_id: "abc-123".into(),
+        };
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(json, r#"{"task_id":"abc-123"}"#);
+
+        let status: AgentStatus =
+            serde_json::from_str(r#"{"agent_id":"building-001","status":"ready","metrics":{}}"#)
+                .unwrap();
+        assert_eq!(status.agent_id, "building-001");
+
+        let delete: TaskDelete = serde_json::from_str(r#"{"task_id":"abc-123"}"#).unwrap();
+        assert_eq!(delete.task_id, TaskId::from("abc-123"));
+        assert!(!delete.cascade);
+    }
+
+    #[test]
+    fn task_delete_cascade_and_deleted_broadcast() {
+        let delete: TaskDelete =
+            serde_json::from_str(r#"{"task_id":"parent","cascade":true}"#).unwrap();
+        assert!(delete.cascade);
+        assert_eq!(
+            serde_json::to_value(&delete).unwrap(),
+            serde_json::json!({ "task_id": "parent", "cascade": true })
+        );
+
+        let deleted = TaskDeleted {
+            task_id: "parent".into(),
+            deleted_ids: vec!["parent".into(), "child-1".into(), "grandchild-1".into()],
+        };
+        let json = serde_json::to_value(&deleted).unwrap();
+        let Message::TaskDeleted(parsed) = Message::from_event(events::TASK_DELETED, json).unwrap()
+        else {
+            panic!("expected TaskDeleted");
+        };
+        assert_eq!(parsed.task_id, delete.task_id);
+        assert_eq!(
+            parsed.deleted_ids,
+            ["parent", "child-1", "grandchild-1"].map(TaskId::from)
+        );
+    }
+
     #[test]
     fn serialize_agent_register() {
         let msg = AgentRegister {
             agent_id: "learning-001".into(),
             role: AgentRole::Learning,
             capabilities: vec!["discover".into(), "evaluate".into()],
+            protocol_version: Some(PROTOCOL_VERSION),
         };
         let json = serde_json::to_string(&msg).unwrap();
         let deserialized: AgentRegister = serde_json::from_str(&json).unwrap();
@@ -539,12 +3173,76 @@ mod tests {
         assert_eq!(deserialized.role, AgentRole::Learning);
     }
 
+    fn register(agent_id: &str, role: AgentRole, capabilities: &[&str]) -> AgentRegister {
+        AgentRegister {
+            agent_id: agent_id.into(),
+            role,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+            protocol_version: Some(PROTOCOL_VERSION),
+        }
+    }
+
+    #[test]
+    fn agent_register_validate_accepts_valid() {
+        register(
+            "learning-001",
+            AgentRole::Learning,
+            &["discover", "evaluate"],
+        )
+        .validate()
+        .unwrap();
+        register("ops-7", AgentRole::User("ops".into()), &[])
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn agent_register_validate_rejects_empty_id() {
+        let err = register("", AgentRole::Learning, &["discover"])
+            .validate()
+            .unwrap_err();
+        assert_eq!(
+            err.violations,
+            [ValidationError::new("agent_id", "must not be empty")]
+        );
+
+        let err = register("Learning_001", AgentRole::Learning, &[])
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.violations[0].field, "agent_id");
+    }
+
+    #[test]
+    fn agent_register_validate_reports_all_violations() {
+        let err = register(
+            "learning-001",
+            AgentRole::Learning,
+            &["discover", "evaluate", "discover"],
+        )
+        .validate()
+        .unwrap_err();
+        assert_eq!(
+            err.violations,
+            [ValidationError::new(
+                "capabilities",
+                "duplicate capability `discover`"
+            )]
+        );
+
+        let err = register("", AgentRole::User(String::new()), &["a", "a"])
+            .validate()
+            .unwrap_err();
+        let fields: Vec<&str> = err.violations.iter().map(|v| v.field).collect();
+        assert_eq!(fields, ["agent_id", "capabilities", "role"]);
+        assert!(err.to_string().contains("user role name must not be empty"));
+    }
+
     #[test]
     fn serialize_pipeline_next() {
         let msg = PipelineNext {
             stage: PipelineStage::Building,
             artifact_id: "skill-xyz".into(),
-            metadata: HashMap::new(),
+            metadata: BTreeMap::new(),
         };
         let json = serde_json::to_string(&msg).unwrap();
         let deserialized: PipelineNext = serde_json::from_str(&json).unwrap();
@@ -560,6 +3258,28 @@ mod tests {
         assert_eq!(de, TaskStatus::InProgress);
     }
 
+    #[test]
+    fn unknown_fields_round_trip_through_extensions() {
+        let task: TaskCreate = serde_json::from_value(serde_json::json!({
+            "task_type": "build",
+            "tenant": "acme",
+        }))
+        .unwrap();
+        assert_eq!(task.extensions["tenant"], "acme");
+        assert_eq!(serde_json::to_value(&task).unwrap()["tenant"], "acme");
+
+        let memory: MemoryStore = serde_json::from_value(serde_json::json!({
+            "scope": "agent",
+            "category": "fact",
+            "tenant": { "id": "acme", "region": "eu" },
+        }))
+        .unwrap();
+        assert_eq!(memory.extensions.len(), 1);
+        let json = serde_json::to_value(&memory).unwrap();
+        assert_eq!(json["tenant"]["region"], "eu");
+        assert_eq!(json["scope"], "agent");
+    }
+
     #[test]
     fn serialize_task_create() {
         let msg = TaskCreate {
@@ -567,6 +3287,9 @@ mod tests {
             agent_id: Some("building-001".into()),
             payload: serde_json::json!({"skill_id": "web-search"}),
             parent_id: None,
+            retry_policy: None,
+            priority: TaskPriority::Normal,
+            extensions: HashMap::new(),
         };
         let json = serde_json::to_string(&msg).unwrap();
         let de: TaskCreate = serde_json::from_str(&json).unwrap();
@@ -602,9 +3325,10 @@ mod tests {
             stage: PipelineStage::Learning,
             agent_id: "learning-001".into(),
             status: PipelineRunStatus::Completed,
-            artifact_id: "artifact-xyz".into(),
+            artifact_id: Some("artifact-xyz".into()),
             output: serde_json::json!({"candidates": 3}),
             error: None,
+            failure_reason: None,
         };
         let json = serde_json::to_string(&result).unwrap();
         let de: PipelineStageResult = serde_json::from_str(&json).unwrap();
@@ -621,14 +3345,37 @@ mod tests {
             stage: PipelineStage::Building,
             agent_id: "building-001".into(),
             status: PipelineRunStatus::Failed,
-            artifact_id: "".into(),
+            artifact_id: None,
             output: serde_json::Value::Null,
             error: Some("build failed: missing dependency".into()),
+            failure_reason: Some(FailureReason::DependencyMissing),
         };
         let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains(r#""failure_reason":"dependency_missing""#));
         let de: PipelineStageResult = serde_json::from_str(&json).unwrap();
         assert_eq!(de.status, PipelineRunStatus::Failed);
         assert_eq!(de.error.unwrap(), "build failed: missing dependency");
+        assert_eq!(de.failure_reason, Some(FailureReason::DependencyMissing));
+    }
+
+    #[test]
+    fn failed_stage_results_need_a_failure_reason() {
+        let mut result = stage_result(PipelineStage::Evaluation, serde_json::json!({}));
+        result.status = PipelineRunStatus::TimedOut;
+        result.artifact_id = None;
+        let err = result.validate().unwrap_err();
+        assert_eq!(err.field, "failure_reason");
+
+        result.failure_reason = Some(FailureReason::Timeout);
+        result.validate().unwrap();
+
+        // Absent on the wire parses as `None` and isn't serialized back.
+        let json =
+            serde_json::to_value(stage_result(PipelineStage::Learning, serde_json::json!({})))
+                .unwrap();
+        assert!(json.get("failure_reason").is_none());
+        let parsed: PipelineStageResult = serde_json::from_value(json).unwrap();
+        assert!(parsed.failure_reason.is_none());
     }
 
     #[test]
@@ -659,6 +3406,18 @@ mod tests {
         assert!(msg.parent_id.is_none());
     }
 
+    #[test]
+    fn minimal_task_create_omits_absent_fields() {
+        let msg: TaskCreate = serde_json::from_str(r#"{"task_type": "test"}"#).unwrap();
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "task_type": "test", "payload": {}, "priority": "normal" })
+        );
+        let round_tripped: TaskCreate = serde_json::from_value(json).unwrap();
+        assert!(round_tripped.agent_id.is_none() && round_tripped.parent_id.is_none());
+    }
+
     #[test]
     fn deserialize_task_list_with_parent_id() {
         let msg: TaskList = serde_json::from_str(r#"{"parent_id": "parent-001"}"#).unwrap();
@@ -707,6 +3466,8 @@ mod tests {
                 },
             ],
             task_id: None,
+            ttl_secs: None,
+            extensions: HashMap::new(),
         };
         let json = serde_json::to_string(&msg).unwrap();
         let de: MemoryStore = serde_json::from_str(&json).unwrap();
@@ -791,4 +3552,2208 @@ mod tests {
         let de: TaskSubtaskSpec = serde_json::from_str(&json).unwrap();
         assert_eq!(de.task_type, "test");
     }
+
+    fn task_record(id: &str, status: &str, parent_id: &str) -> TaskRecord {
+        TaskRecord {
+            id: id.into(),
+            task_type: "build".into(),
+            status: status.into(),
+            agent_id: "building-001".into(),
+            payload: serde_json::json!({}),
+            parent_id: parent_id.into(),
+            priority: TaskPriority::Normal,
+            created_at: "2026-01-01T00:00:00Z".into(),
+            updated_at: "2026-01-01T00:00:00Z".into(),
+        }
+    }
+
+    #[test]
+    fn task_record_assigned_agent() {
+        let record = task_record("t-1", "running", "");
+        assert_eq!(record.assigned_agent(), Some("building-001"));
+
+        let unassigned: TaskRecord = serde_json::from_str(
+            r#"{"id":"t-2","task_type":"build","status":"pending","payload":{},"created_at":"2026-01-01T00:00:00Z","updated_at":"2026-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(unassigned.agent_id, "");
+        assert_eq!(unassigned.assigned_agent(), None);
+
+        let empty = TaskRecord {
+            agent_id: String::new(),
+            ..record
+        };
+        assert_eq!(empty.assigned_agent(), None);
+    }
+
+    #[test]
+    fn task_index_queries_by_status_and_parent() {
+        let index = TaskIndex::new(vec![
+            task_record("parent", "decomposed", ""),
+            task_record("child-1", "in_progress", "parent"),
+            task_record("child-2", "pending", "parent"),
+        ]);
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.get("child-2").unwrap().status, "pending");
+        assert!(index.get("missing").is_none());
+
+        let children: Vec<&str> = index
+            .children_of("parent")
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(children, vec!["child-1", "child-2"]);
+        assert!(index.children_of("child-1").is_empty());
+
+        let in_progress = index.by_status(TaskStatus::InProgress);
+        assert_eq!(in_progress.len(), 1);
+        assert_eq!(in_progress[0].id, "child-1");
+        assert!(index.by_status(TaskStatus::Failed).is_empty());
+    }
+
+    #[test]
+    fn agent_health_merge_replaces_by_name() {
+        let check = |name: &str, healthy: bool| HealthCheck {
+            name: name.into(),
+            endpoint: format!("https://{name}.example"),
+            healthy,
+            latency_ms: None,
+            error: None,
+        };
+        let mut periodic = AgentHealth {
+            agent_id: "learning-001".into(),
+            health_checks: vec![check("openai", true), check("anthropic", true)],
+        };
+        let on_demand = AgentHealth {
+            agent_id: "learning-001".into(),
+            health_checks: vec![check("anthropic", false), check("ollama", true)],
+        };
+        periodic.merge(on_demand).unwrap();
+
+        let names: Vec<&str> = periodic
+            .health_checks
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, ["openai", "anthropic", "ollama"]);
+        assert_eq!(periodic.failing(), ["anthropic"]);
+
+        let other_agent = AgentHealth {
+            agent_id: "building-001".into(),
+            health_checks: vec![check("openai", false)],
+        };
+        let err = periodic.merge(other_agent).unwrap_err();
+        assert_eq!(err.field, "agent_id");
+        assert_eq!(periodic.health_checks.len(), 3);
+        assert!(periodic.health_checks[0].healthy);
+    }
+
+    #[test]
+    fn health_summary_from_mixed_report() {
+        let health = AgentHealth {
+            agent_id: "learning-001".into(),
+            health_checks: vec![
+                HealthCheck {
+                    name: "openai".into(),
+                    endpoint: "https://api.openai.com/v1".into(),
+                    healthy: true,
+                    latency_ms: Some(120),
+                    error: None,
+                },
+                HealthCheck {
+                    name: "anthropic".into(),
+                    endpoint: "https://api.anthropic.com/v1".into(),
+                    healthy: false,
+                    latency_ms: Some(950),
+                    error: Some("503 Service Unavailable".into()),
+                },
+                HealthCheck {
+                    name: "ollama".into(),
+                    endpoint: "http://localhost:11434".into(),
+                    healthy: false,
+                    latency_ms: None,
+                    error: Some("connection refused".into()),
+                },
+            ],
+        };
+        let summary = HealthSummary::from(&health);
+        assert_eq!(summary.agent_id, "learning-001");
+        assert!(!summary.healthy);
+        assert_eq!(summary.failing, vec!["anthropic", "ollama"]);
+        assert_eq!(summary.max_latency_ms, Some(950));
+    }
+
+    fn memory_store(scope: MemoryScope, task_id: Option<&str>) -> MemoryStore {
+        MemoryStore {
+            scope,
+            category: MemoryCategory::Case,
+            key: String::new(),
+            metadata: serde_json::json!({}),
+            tags: vec![],
+            agent_id: "learning-001".into(),
+            run_id: String::new(),
+            skill_id: String::new(),
+            relevance_score: 0.5,
+            tiers: vec![MemoryTierEntry {
+                tier: "l0".into(),
+                content: "retry with smaller batch".into(),
+            }],
+            task_id: task_id.map(Into::into),
+            ttl_secs: None,
+            extensions: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn memory_category_default_ttl() {
+        assert_eq!(
+            MemoryCategory::Event.default_ttl(),
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        for category in [
+            MemoryCategory::Case,
+            MemoryCategory::Pattern,
+            MemoryCategory::Fact,
+        ] {
+            assert_eq!(category.default_ttl(), None, "{category}");
+        }
+    }
+
+    #[test]
+    fn memory_store_ttl_override() {
+        let mut store = memory_store(MemoryScope::Agent, None);
+        assert_eq!(store.ttl(), None);
+        assert!(!serde_json::to_string(&store).unwrap().contains("ttl_secs"));
+
+        store.category = MemoryCategory::Event;
+        assert_eq!(store.ttl(), MemoryCategory::Event.default_ttl());
+
+        let json = serde_json::to_value(&store).unwrap();
+        let mut with_ttl = json.clone();
+        with_ttl["ttl_secs"] = serde_json::json!(3600);
+        let store: MemoryStore = serde_json::from_value(with_ttl).unwrap();
+        assert_eq!(store.ttl_secs, Some(3600));
+        assert_eq!(store.ttl(), Some(Duration::from_secs(3600)));
+
+        let store: MemoryStore = serde_json::from_value(json).unwrap();
+        assert_eq!(store.ttl_secs, None);
+    }
+
+    #[test]
+    fn serialize_memory_scope_task() {
+        let json = serde_json::to_string(&MemoryScope::Task).unwrap();
+        assert_eq!(json, r#""task""#);
+        let de: MemoryScope = serde_json::from_str(&json).unwrap();
+        assert_eq!(de, MemoryScope::Task);
+    }
+
+    #[test]
+    fn task_scoped_memory_requires_task_id() {
+        let err = memory_store(MemoryScope::Task, None)
+            .validate()
+            .unwrap_err();
+        assert_eq!(err.field, "task_id");
+        assert!(
+            memory_store(MemoryScope::Task, Some(""))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            memory_store(MemoryScope::Task, Some("task-001"))
+                .validate()
+                .is_ok()
+        );
+        assert!(memory_store(MemoryScope::Agent, None).validate().is_ok());
+    }
+
+    #[test]
+    fn retry_policy_exponential_sequence_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 6,
+            backoff: Backoff::Exponential {
+                base_secs: 1,
+                factor: 2.0,
+                max_secs: 10,
+            },
+        };
+        let delays: Vec<u64> = (0..6)
+            .map(|n| policy.delay_for_attempt(n).as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+        assert_eq!(policy.delay_for_attempt(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn retry_policy_extreme_wire_values_do_not_panic() {
+        let exponential = |base_secs, factor, max_secs| RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Exponential {
+                base_secs,
+                factor,
+                max_secs,
+            },
+        };
+        assert_eq!(
+            exponential(1, 2.0, u64::MAX).delay_for_attempt(100),
+            Duration::from_secs(u64::MAX)
+        );
+        assert_eq!(
+            exponential(0, 1e300, 10).delay_for_attempt(2),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn retry_policy_fixed_and_wire_format() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            backoff: Backoff::Fixed { secs: 5 },
+        };
+        assert_eq!(policy.delay_for_attempt(2), Duration::from_secs(5));
+        let json = serde_json::to_value(&policy).unwrap();
+        assert_eq!(
+            json["backoff"],
+            serde_json::json!({"kind": "fixed", "secs": 5})
+        );
+
+        let msg: TaskCreate = serde_json::from_str(
+            r#"{"task_type":"build","retry_policy":{"max_attempts":2,"backoff":{"kind":"exponential","base_secs":1,"factor":3.0,"max_secs":60}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            msg.retry_policy.unwrap().delay_for_attempt(1),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn payload_get_reads_nested_pointer() {
+        let msg = TaskCreate {
+            task_type: "build".into(),
+            agent_id: None,
+            payload: serde_json::json!({"config": {"limit": 25, "tags": ["a", "b"]}}),
+            parent_id: None,
+            retry_policy: None,
+            priority: TaskPriority::Normal,
+            extensions: HashMap::new(),
+        };
+        assert_eq!(
+            msg.payload_get("/config/limit"),
+            Some(&serde_json::json!(25))
+        );
+        assert_eq!(
+            msg.payload_get("/config/tags/1"),
+            Some(&serde_json::json!("b"))
+        );
+        assert!(msg.payload_get("/config/missing").is_none());
+    }
+
+    #[test]
+    fn metadata_get_reads_nested_pointer() {
+        let record: MemoryRecord = serde_json::from_value(serde_json::json!({
+            "id": "mem-001",
+            "scope": "agent",
+            "category": "fact",
+            "key": "limits",
+            "metadata": {"config": {"limit": 10}},
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z"
+        }))
+        .unwrap();
+        assert_eq!(
+            record.metadata_get("/config/limit"),
+            Some(&serde_json::json!(10))
+        );
+
+        let eval: TaskEvaluate = serde_json::from_value(serde_json::json!({
+            "task_id": "t1",
+            "task_type": "build",
+            "metadata": {"config": {"limit": 3}}
+        }))
+        .unwrap();
+        assert_eq!(
+            eval.metadata_get("/config/limit"),
+            Some(&serde_json::json!(3))
+        );
+    }
+
+    #[test]
+    fn display_matches_serde_labels() {
+        assert_eq!(format!("{}", PipelineStage::Building), "building");
+        assert_eq!(PipelineStage::PreLoad.to_string(), "pre_load");
+        assert_eq!(PipelineRunStatus::TimedOut.to_string(), "timed_out");
+        assert_eq!(TaskStatus::InProgress.as_str(), "in_progress");
+        assert_eq!(MemoryCategory::Preference.to_string(), "preference");
+
+        let stage = PipelineStage::SkillManage;
+        assert_eq!(
+            serde_json::to_string(&stage).unwrap(),
+            format!("\"{stage}\"")
+        );
+    }
+
+    #[test]
+    fn from_str_parses_serde_labels() {
+        assert_eq!(
+            "in_progress".parse::<TaskStatus>(),
+            Ok(TaskStatus::InProgress)
+        );
+        assert_eq!("busy".parse::<RunnerStatus>(), Ok(RunnerStatus::Busy));
+        assert_eq!(
+            "pre_load".parse::<PipelineStage>(),
+            Ok(PipelineStage::PreLoad)
+        );
+        assert_eq!(
+            "timed_out".parse::<PipelineRunStatus>(),
+            Ok(PipelineRunStatus::TimedOut)
+        );
+        assert_eq!("task".parse::<MemoryScope>(), Ok(MemoryScope::Task));
+        assert_eq!("fact".parse::<MemoryCategory>(), Ok(MemoryCategory::Fact));
+    }
+
+    #[test]
+    fn from_str_rejects_unknown_values() {
+        let err = "InProgress".parse::<TaskStatus>().unwrap_err();
+        assert_eq!(err.enum_name, "TaskStatus");
+        assert_eq!(err.value, "InProgress");
+        assert!(err.to_string().contains("in_progress"));
+
+        assert!("idle".parse::<RunnerStatus>().is_err());
+        assert!("deploy".parse::<PipelineStage>().is_err());
+        assert!("paused".parse::<PipelineRunStatus>().is_err());
+        assert!("global".parse::<MemoryScope>().is_err());
+        assert!("".parse::<MemoryCategory>().is_err());
+    }
+
+    #[test]
+    fn register_without_protocol_version_deserializes() {
+        let msg: AgentRegister = serde_json::from_str(
+            r#"{"agent_id":"learning-001","role":"learning","capabilities":[]}"#,
+        )
+        .unwrap();
+        assert!(msg.protocol_version.is_none());
+    }
+
+    #[test]
+    fn protocol_version_compatibility() {
+        assert!(is_compatible(PROTOCOL_VERSION));
+        assert!(!is_compatible(PROTOCOL_VERSION + 1));
+    }
+
+    fn debug_chunk(request_id: &str, chunk_index: u32, delta: &str, is_final: bool) -> DebugStream {
+        DebugStream {
+            request_id: request_id.into(),
+            delta: delta.into(),
+            chunk_index,
+            is_final,
+        }
+    }
+
+    #[test]
+    fn debug_stream_assembles_ordered_chunks() {
+        let mut assembler = DebugStreamAssembler::new();
+        assert_eq!(
+            assembler.push(&debug_chunk("req-1", 0, "Hello", false)),
+            AssembleState::Pending
+        );
+        assert_eq!(
+            assembler.push(&debug_chunk("req-2", 0, "Other", false)),
+            AssembleState::Pending
+        );
+        assert_eq!(
+            assembler.push(&debug_chunk("req-1", 1, ", world", true)),
+            AssembleState::Complete("Hello, world".into())
+        );
+        assert_eq!(assembler.pending(), 1);
+    }
+
+    #[test]
+    fn debug_stream_rejects_out_of_order_chunk() {
+        let mut assembler = DebugStreamAssembler::new();
+        assembler.push(&debug_chunk("req-1", 0, "a", false));
+        assert_eq!(
+            assembler.push(&debug_chunk("req-1", 2, "c", false)),
+            AssembleState::OutOfOrder {
+                expected: 1,
+                got: 2
+            }
+        );
+        assert_eq!(assembler.pending(), 0);
+    }
+
+    fn queued_task(id: &str, priority: TaskPriority, created_at: &str) -> TaskRecord {
+        TaskRecord {
+            priority,
+            created_at: created_at.into(),
+            ..task_record(id, "pending", "")
+        }
+    }
+
+    #[test]
+    fn task_queue_pops_critical_before_older_normal() {
+        let mut queue = TaskQueue::new();
+        queue.push(queued_task(
+            "old-normal",
+            TaskPriority::Normal,
+            "2026-01-01T00:00:00Z",
+        ));
+        queue.push(queued_task(
+            "new-critical",
+            TaskPriority::Critical,
+            "2026-01-02T00:00:00Z",
+        ));
+        queue.push(queued_task(
+            "low",
+            TaskPriority::Low,
+            "2025-12-01T00:00:00Z",
+        ));
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.pop().unwrap().id, "new-critical");
+        assert_eq!(queue.pop().unwrap().id, "old-normal");
+        assert_eq!(queue.pop().unwrap().id, "low");
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn task_queue_is_fifo_within_priority() {
+        let mut queue = TaskQueue::new();
+        queue.push(queued_task(
+            "second",
+            TaskPriority::High,
+            "2026-01-01T00:00:02Z",
+        ));
+        queue.push(queued_task(
+            "first",
+            TaskPriority::High,
+            "2026-01-01T00:00:01+00:00",
+        ));
+        queue.push(queued_task(
+            "third",
+            TaskPriority::High,
+            "2026-01-01T00:00:02Z",
+        ));
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop()).map(|t| t.id).collect();
+        assert_eq!(order, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn task_priority_orders_and_defaults() {
+        assert!(TaskPriority::Critical > TaskPriority::High);
+        assert!(TaskPriority::Normal > TaskPriority::Low);
+        let msg: TaskCreate = serde_json::from_str(r#"{"task_type":"build"}"#).unwrap();
+        assert_eq!(msg.priority, TaskPriority::Normal);
+    }
+
+    #[test]
+    fn config_update_for_uses_config_hash() {
+        let config = serde_json::json!({"server": {"host": "0.0.0.0", "port": 8080}});
+        let update = config_update_for("gateway", &config).unwrap();
+        assert_eq!(update.config_type, "gateway");
+        assert_eq!(
+            update.new_config_hash,
+            crate::config::hash_config(&config).unwrap()
+        );
+    }
+
+    #[test]
+    fn message_routes_task_create_by_event_name() {
+        let msg = Message::from_event(
+            events::TASK_CREATE,
+            serde_json::json!({"task_type": "build", "payload": {"skill_id": "web-search"}}),
+        )
+        .unwrap();
+        assert_eq!(msg.event_name(), events::TASK_CREATE);
+        match &msg {
+            Message::TaskCreate(create) => assert_eq!(create.task_type, "build"),
+            other => panic!("expected TaskCreate, got {other:?}"),
+        }
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["task_type"], "build");
+    }
+
+    #[test]
+    fn message_rejects_unknown_event_and_bad_payload() {
+        let err = Message::from_event("task:teleport", serde_json::json!({})).unwrap_err();
+        assert!(matches!(err, MessageError::UnknownEvent(ref e) if e == "task:teleport"));
+
+        let err = Message::from_event(events::TASK_GET, serde_json::json!({})).unwrap_err();
+        assert!(matches!(err, MessageError::InvalidPayload { .. }));
+    }
+
+    #[test]
+    fn builtin_roles_map_to_distinct_rooms() {
+        let roles = AgentRole::builtins();
+        assert_eq!(roles.len(), 5);
+        assert!(!roles.iter().any(AgentRole::is_user));
+
+        let rooms: std::collections::HashSet<String> = roles.iter().map(role_room).collect();
+        assert_eq!(rooms.len(), roles.len());
+        assert!(rooms.contains("role:pre_load"));
+        assert_eq!(role_room(&AgentRole::User("ops".into())), "role:ops");
+    }
+
+    /// Serve one HTTP response with `status_line` on a local port and return
+    /// its URL.
+    #[cfg(feature = "reqwest")]
+    fn one_shot_http_server(status_line: &'static str) -> String {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/health", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let response =
+                format!("HTTP/1.1 {status_line}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn health_check_probe_reports_status() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let client = reqwest::Client::new();
+
+        let ok_url = one_shot_http_server("200 OK");
+        let ok = runtime.block_on(HealthCheck::probe("api", &ok_url, &client));
+        assert!(ok.healthy);
+        assert_eq!(ok.endpoint, ok_url);
+        assert!(ok.latency_ms.is_some());
+        assert!(ok.error.is_none());
+
+        let failing_url = one_shot_http_server("500 Internal Server Error");
+        let failing = runtime.block_on(HealthCheck::probe("api", &failing_url, &client));
+        assert!(!failing.healthy);
+        assert!(failing.latency_ms.is_some());
+        assert_eq!(
+            failing.error.as_deref(),
+            Some("HTTP 500 Internal Server Error")
+        );
+    }
+
+    #[test]
+    fn evaluation_detail_reads_sections() {
+        let summary: TaskSummary = serde_json::from_value(serde_json::json!({
+            "task_id": "task-1",
+            "agent_id": "evaluation-001",
+            "summary": "ok",
+            "evaluation": {
+                "strengths": ["clear structure"],
+                "weaknesses": ["no tests", "slow"],
+                "rubric_scores": { "correctness": 0.9, "style": 0.7 },
+                "model": "reviewer-v2",
+            },
+        }))
+        .unwrap();
+        let detail = summary.evaluation_detail().unwrap();
+        assert_eq!(detail.strengths, ["clear structure"]);
+        assert_eq!(detail.weaknesses, ["no tests", "slow"]);
+        assert_eq!(detail.rubric_scores["correctness"], 0.9);
+        assert_eq!(detail.rubric_scores.len(), 2);
+    }
+
+    #[test]
+    fn evaluation_detail_tolerates_missing_sections() {
+        let mut summary: TaskSummary = serde_json::from_value(serde_json::json!({
+            "task_id": "task-1",
+            "agent_id": "evaluation-001",
+            "summary": "ok",
+            "evaluation": { "strengths": ["fast"] },
+        }))
+        .unwrap();
+        let detail = summary.evaluation_detail().unwrap();
+        assert_eq!(detail.strengths, ["fast"]);
+        assert!(detail.weaknesses.is_empty());
+        assert!(detail.rubric_scores.is_empty());
+
+        summary.evaluation = serde_json::Value::Null;
+        assert_eq!(
+            summary.evaluation_detail().unwrap(),
+            EvaluationDetail::default()
+        );
+
+        summary.evaluation = serde_json::json!({ "rubric_scores": ["correctness"] });
+        assert!(summary.evaluation_detail().is_err());
+    }
+
+    #[test]
+    fn score_accepts_numbers_and_percent_strings() {
+        let summary_score = |score: serde_json::Value| {
+            serde_json::from_value::<TaskSummary>(serde_json::json!({
+                "task_id": "task-1",
+                "agent_id": "evaluation-001",
+                "summary": "ok",
+                "score": score,
+            }))
+            .map(|s| s.score)
+        };
+        assert_eq!(summary_score(serde_json::json!(0.85)).unwrap(), Some(0.85));
+        assert_eq!(summary_score(serde_json::json!(1)).unwrap(), Some(1.0));
+        assert_eq!(
+            summary_score(serde_json::json!("0.85")).unwrap(),
+            Some(0.85)
+        );
+        assert_eq!(summary_score(serde_json::json!("85%")).unwrap(), Some(0.85));
+        assert_eq!(summary_score(serde_json::json!(null)).unwrap(), None);
+        assert!(summary_score(serde_json::json!("great")).is_err());
+        assert!(summary_score(serde_json::json!("NaN")).is_err());
+
+        let report: AgentSkillReport = serde_json::from_value(serde_json::json!({
+            "agent_id": "skill-manage-001",
+            "skill_id": "search",
+            "result": "success",
+            "score": " 42.5 % ",
+        }))
+        .unwrap();
+        assert_eq!(report.score, Some(0.425));
+        let report: AgentSkillReport = serde_json::from_value(serde_json::json!({
+            "agent_id": "skill-manage-001",
+            "skill_id": "search",
+            "result": "success",
+        }))
+        .unwrap();
+        assert_eq!(report.score, None);
+    }
+
+    #[test]
+    fn legacy_role_names_deserialize() {
+        for (legacy, current) in [("skillmanage", "skill_manage"), ("preload", "pre_load")] {
+            let legacy: AgentRole = serde_json::from_value(serde_json::json!(legacy)).unwrap();
+            let current: AgentRole = serde_json::from_value(serde_json::json!(current)).unwrap();
+            assert_eq!(legacy, current);
+            assert_eq!(serde_json::to_value(&legacy).unwrap(), current.as_str());
+        }
+    }
+
+    #[test]
+    fn task_invite_acceptance() {
+        let invite = TaskInvite {
+            task_id: "task-1".into(),
+            task_type: "summarize".into(),
+            payload: serde_json::json!({}),
+        };
+        let handled = ["summarize".to_string(), "search".to_string()];
+        let decide = |invite: &TaskInvite, status| {
+            should_accept(invite, &AgentRole::Learning, status, &handled)
+        };
+
+        assert_eq!(decide(&invite, RunnerStatus::Ready), AcceptDecision::Accept);
+        assert_eq!(
+            decide(&invite, RunnerStatus::Starting),
+            AcceptDecision::Decline("agent is starting".into())
+        );
+        assert_eq!(
+            decide(&invite, RunnerStatus::Busy),
+            AcceptDecision::Decline("agent is busy".into())
+        );
+
+        let unhandled = TaskInvite {
+            task_type: "compile".into(),
+            ..invite.clone()
+        };
+        assert!(matches!(
+            decide(&unhandled, RunnerStatus::Ready),
+            AcceptDecision::Decline(reason) if reason.contains("`compile`")
+        ));
+
+        let for_building = TaskInvite {
+            payload: serde_json::json!({ "role": "building" }),
+            ..invite
+        };
+        assert!(matches!(
+            decide(&for_building, RunnerStatus::Ready),
+            AcceptDecision::Decline(_)
+        ));
+    }
+
+    #[test]
+    fn payload_limits_scale_with_event_kind() {
+        let evaluate = events::max_payload_bytes(events::TASK_EVALUATE);
+        for liveness in [events::AGENT_STATUS, events::AGENT_HEALTH_SUMMARY] {
+            assert_eq!(events::max_payload_bytes(liveness), 16 * 1024);
+            assert!(evaluate > events::max_payload_bytes(liveness));
+        }
+        assert!(
+            events::max_payload_bytes(events::AGENT_STATUS)
+                < events::max_payload_bytes(events::TASK_OUTPUT)
+        );
+        assert_eq!(
+            events::max_payload_bytes("custom:event"),
+            events::DEFAULT_MAX_PAYLOAD_BYTES
+        );
+    }
+
+    #[test]
+    fn user_role_rejects_empty_and_builtin_names() {
+        assert_eq!(AgentRole::user(""), Err(RoleError::Empty));
+        assert_eq!(
+            AgentRole::user("building"),
+            Err(RoleError::ShadowsBuiltin("building".into()))
+        );
+        let ops = AgentRole::user("ops").unwrap();
+        assert!(ops.is_user());
+        assert_eq!(ops, AgentRole::User("ops".into()));
+        assert!(!AgentRole::Building.is_user());
+    }
+
+    #[test]
+    fn pipeline_stage_progression() {
+        assert_eq!(
+            PipelineStage::Learning.next(),
+            Some(PipelineStage::Building)
+        );
+        assert_eq!(
+            PipelineStage::PreLoad.next(),
+            Some(PipelineStage::Evaluation)
+        );
+        assert_eq!(PipelineStage::Evaluation.next(), None);
+        assert_eq!(PipelineStage::SkillManage.next(), None);
+
+        let order = PipelineStage::pipeline_order();
+        assert!(order.windows(2).all(|w| w[0].order() < w[1].order()));
+        assert!(
+            order
+                .windows(2)
+                .all(|w| w[0].next().as_ref() == Some(&w[1]))
+        );
+        assert!(
+            order
+                .iter()
+                .all(|s| s.order() < PipelineStage::SkillManage.order())
+        );
+    }
+
+    #[test]
+    fn pipeline_stage_all_lists_every_stage() {
+        let all = PipelineStage::all();
+        assert_eq!(all.len(), 5);
+        for stage in all {
+            assert_eq!(stage.as_str().parse::<PipelineStage>().as_ref(), Ok(stage));
+        }
+    }
+
+    #[test]
+    fn failed_stage_result_without_artifact() {
+        let json = r#"{"run_id":"run-1","stage":"building","agent_id":"building-001","status":"failed","artifact_id":"","output":null,"error":"boom","failure_reason":"crash"}"#;
+        let result: PipelineStageResult = serde_json::from_str(json).unwrap();
+        assert!(result.artifact_id.is_none());
+        assert!(result.validate().is_ok());
+        let out = serde_json::to_value(&result).unwrap();
+        assert_eq!(out["artifact_id"], "");
+
+        let missing = json.replace(r#""artifact_id":"","#, "");
+        let result: PipelineStageResult = serde_json::from_str(&missing).unwrap();
+        assert!(result.artifact_id.is_none());
+    }
+
+    #[test]
+    fn completed_stage_result_requires_artifact() {
+        let json = r#"{"run_id":"run-1","stage":"building","agent_id":"building-001","status":"completed","artifact_id":"artifact-xyz","output":{}}"#;
+        let mut result: PipelineStageResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.artifact_id.as_deref(), Some("artifact-xyz"));
+        assert!(result.validate().is_ok());
+
+        result.artifact_id = None;
+        assert_eq!(result.validate().unwrap_err().field, "artifact_id");
+    }
+
+    fn task_output(chunk_index: u32, delta: &str) -> TaskOutput {
+        TaskOutput {
+            task_id: "task-001".into(),
+            request_id: "req-001".into(),
+            source: "pty".into(),
+            delta: delta.into(),
+            chunk_index,
+            is_final: false,
+            encoding: OutputEncoding::Utf8,
+        }
+    }
+
+    #[test]
+    fn task_evaluate_from_out_of_order_outputs() {
+        let outputs = vec![
+            task_output(2, "world\n"),
+            task_output(0, "hello"),
+            task_output(1, ", "),
+        ];
+        let eval = TaskEvaluate::from_outputs("task-001", "build", &outputs, Some(0), Some(1500));
+        assert_eq!(eval.output_summary, "hello, world\n");
+        assert_eq!(eval.task_id, "task-001");
+        assert_eq!(eval.exit_code, Some(0));
+        assert_eq!(eval.latency_ms, Some(1500));
+    }
+
+    #[test]
+    fn task_evaluate_decodes_mixed_encodings() {
+        let encoded = |chunk_index, delta: &str, encoding| TaskOutput {
+            encoding,
+            ..task_output(chunk_index, delta)
+        };
+        let outputs = vec![
+            encoded(0, "build: ", OutputEncoding::Utf8),
+            encoded(1, "\x1b[32mok\x1b[0m ", OutputEncoding::Ansi),
+            // Separately padded chunks: "do" and "ne".
+            encoded(2, "ZG8=", OutputEncoding::Base64),
+            encoded(3, "bmU=", OutputEncoding::Base64),
+        ];
+        let eval = TaskEvaluate::from_outputs("t", "build", &outputs, None, None);
+        assert_eq!(eval.output_summary, "build: ok done");
+    }
+
+    #[test]
+    fn task_evaluate_truncates_summary() {
+        let big = "é".repeat(DEFAULT_OUTPUT_LIMIT);
+        let eval = TaskEvaluate::from_outputs("t", "build", &[task_output(0, &big)], None, None);
+        assert!(eval.output_summary.len() <= DEFAULT_OUTPUT_LIMIT);
+        assert!(eval.output_summary.chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn roundtrip_memory_store_batch() {
+        let batch = MemoryStoreBatch {
+            memories: vec![
+                memory_store(MemoryScope::Agent, None),
+                memory_store(MemoryScope::Task, Some("task-001")),
+            ],
+        };
+        assert!(batch.validate(DEFAULT_MEMORY_BATCH_LIMIT).is_ok());
+        assert!(batch.validate(1).is_err());
+
+        let json = serde_json::to_string(&batch).unwrap();
+        let de: MemoryStoreBatch = serde_json::from_str(&json).unwrap();
+        let stores = de.into_stores();
+        assert_eq!(stores.len(), 2);
+        assert_eq!(stores[1].task_id.as_deref(), Some("task-001"));
+    }
+
+    #[test]
+    fn empty_memory_store_batch_is_invalid() {
+        let batch = MemoryStoreBatch { memories: vec![] };
+        let err = batch.validate(DEFAULT_MEMORY_BATCH_LIMIT).unwrap_err();
+        assert_eq!(err.field, "memories");
+    }
+
+    fn timed_check(latency_ms: Option<u64>) -> HealthCheck {
+        HealthCheck {
+            name: "probe".into(),
+            endpoint: "http://localhost".into(),
+            healthy: true,
+            latency_ms,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn latency_percentiles_over_known_distribution() {
+        // 1..=100 ms, shuffled order, plus unmeasured checks that must be ignored.
+        let mut checks: Vec<HealthCheck> =
+            (1..=100).rev().map(|ms| timed_check(Some(ms))).collect();
+        checks.push(timed_check(None));
+        checks.insert(10, timed_check(None));
+
+        let stats = latency_percentiles(&checks).unwrap();
+        assert_eq!(
+            stats,
+            LatencyStats {
+                p50: 50,
+                p95: 95,
+                p99: 99,
+                max: 100,
+                count: 100,
+            }
+        );
+
+        let single = latency_percentiles(&[timed_check(Some(42))]).unwrap();
+        assert_eq!((single.p50, single.p99, single.max), (42, 42, 42));
+    }
+
+    #[test]
+    fn latency_percentiles_none_without_measurements() {
+        assert!(latency_percentiles(&[]).is_none());
+        assert!(latency_percentiles(&[timed_check(None)]).is_none());
+    }
+
+    #[test]
+    fn room_kind_parses_known_prefixes() {
+        assert_eq!("kernel".parse(), Ok(RoomKind::Kernel));
+        assert_eq!("task:abc".parse(), Ok(RoomKind::Task("abc".into())));
+        assert_eq!(
+            "role:learning".parse(),
+            Ok(RoomKind::Role(AgentRole::Learning))
+        );
+        assert_eq!(
+            RoomKind::from("role:ops"),
+            RoomKind::Role(AgentRole::User("ops".into()))
+        );
+        assert_eq!(RoomKind::Task("abc".into()).to_string(), "task:abc");
+    }
+
+    #[test]
+    fn room_kind_keeps_custom_rooms() {
+        assert_eq!(
+            RoomKind::from("dashboard:ops"),
+            RoomKind::Unknown("dashboard:ops".into())
+        );
+        assert_eq!(RoomKind::from("task:"), RoomKind::Unknown("task:".into()));
+    }
+
+    #[test]
+    fn agent_status_delta_round_trip() {
+        let prev = AgentStatus {
+            agent_id: "a1".into(),
+            status: RunnerStatus::Ready,
+            metrics: BTreeMap::from([
+                ("cpu".to_string(), serde_json::json!(0.2)),
+                ("mem".to_string(), serde_json::json!(512)),
+                ("queue".to_string(), serde_json::json!(3)),
+            ]),
+        };
+        let next = AgentStatus {
+            agent_id: "a1".into(),
+            status: RunnerStatus::Busy,
+            metrics: BTreeMap::from([
+                ("cpu".to_string(), serde_json::json!(0.9)),
+                ("mem".to_string(), serde_json::json!(512)),
+                ("disk".to_string(), serde_json::json!(10)),
+            ]),
+        };
+
+        let delta = next.diff(&prev);
+        assert_eq!(delta.status, Some(RunnerStatus::Busy));
+        assert_eq!(delta.changed_metrics.len(), 2);
+        assert!(!delta.changed_metrics.contains_key("mem"));
+        assert_eq!(delta.removed_metrics, vec!["queue".to_string()]);
+
+        let mut rebuilt = prev.clone();
+        delta.apply_to(&mut rebuilt);
+        assert_eq!(rebuilt.status, next.status);
+        assert_eq!(rebuilt.metrics, next.metrics);
+        assert!(next.diff(&next).is_empty());
+    }
+
+    fn stage_result(stage: PipelineStage, output: serde_json::Value) -> PipelineStageResult {
+        PipelineStageResult {
+            run_id: "run-1".into(),
+            stage,
+            agent_id: "a1".into(),
+            status: PipelineRunStatus::Completed,
+            artifact_id: Some("art-1".into()),
+            output,
+            error: None,
+            failure_reason: None,
+        }
+    }
+
+    #[test]
+    fn learning_output_deserializes_for_learning_stage() {
+        let result = stage_result(
+            PipelineStage::Learning,
+            serde_json::json!({ "candidates": ["summarize", "translate"] }),
+        );
+        let output = result.learning_output().unwrap();
+        assert_eq!(output.candidates, vec!["summarize", "translate"]);
+    }
+
+    #[test]
+    fn stage_output_rejects_wrong_stage_and_bad_shape() {
+        let result = stage_result(
+            PipelineStage::Building,
+            serde_json::json!({ "artifact_path": "/tmp/skill" }),
+        );
+        assert!(matches!(
+            result.learning_output(),
+            Err(StageOutputError::WrongStage {
+                expected: PipelineStage::Learning,
+                actual: PipelineStage::Building,
+            })
+        ));
+        assert_eq!(
+            result.building_output().unwrap().artifact_path,
+            "/tmp/skill"
+        );
+
+        let bad = stage_result(
+            PipelineStage::Evaluation,
+            serde_json::json!({ "score": "high" }),
+        );
+        assert!(matches!(
+            bad.evaluation_output(),
+            Err(StageOutputError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn event_name_interns_known_names() {
+        let known = EventName::from(events::TASK_CREATE);
+        assert!(known.is_static());
+        assert_eq!(
+            serde_json::to_string(&known).unwrap(),
+            serde_json::to_string(events::TASK_CREATE).unwrap()
+        );
+
+        let parsed: EventName = serde_json::from_str(r#""task:create""#).unwrap();
+        assert!(parsed.is_static());
+        assert_eq!(parsed, known);
+
+        let custom = EventName::from("plugin:ping".to_string());
+        assert!(!custom.is_static());
+        assert_eq!(custom, "plugin:ping");
+    }
+
+    #[test]
+    fn envelope_carries_message_event() {
+        let envelope = Envelope::from(Message::TaskGet(TaskGet {
+            task_id: "t1".into(),
+        }));
+        assert_eq!(envelope.event, events::TASK_GET);
+        let mut json = serde_json::to_value(&envelope).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        let message_id = fields.remove("message_id").unwrap();
+        assert_eq!(message_id.as_str().unwrap().len(), 36);
+        let emitted_at = fields.remove("emitted_at").unwrap();
+        assert!(DateTime::parse_from_rfc3339(emitted_at.as_str().unwrap()).is_ok());
+        assert_eq!(
+            json,
+            serde_json::json!({ "event": "task:get", "payload": { "task_id": "t1" } })
+        );
+
+        let legacy: Envelope<TaskGet> = serde_json::from_value(
+            serde_json::json!({ "event": "task:get", "payload": { "task_id": "t1" } }),
+        )
+        .unwrap();
+        assert!(legacy.message_id.is_empty() && legacy.emitted_at.is_empty());
+    }
+
+    #[test]
+    fn envelope_second_observation_is_duplicate() {
+        let mut cache = DedupCache::new(16);
+        let envelope = Envelope::new(events::TASK_GET, "t1");
+        let redelivered = envelope.clone();
+        assert!(!envelope.is_duplicate(&mut cache));
+        assert!(redelivered.is_duplicate(&mut cache));
+        assert!(!Envelope::new(events::TASK_GET, "t1").is_duplicate(&mut cache));
+
+        let mut legacy = envelope;
+        legacy.message_id.clear();
+        assert!(!legacy.is_duplicate(&mut cache));
+        assert!(!legacy.is_duplicate(&mut cache));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn dedup_cache_evicts_least_recently_seen() {
+        let mut cache = DedupCache::new(2);
+        assert!(!cache.observe("a"));
+        assert!(!cache.observe("b"));
+        // Seeing `a` again makes `b` the least recent.
+        assert!(cache.observe("a"));
+        assert!(!cache.observe("c"));
+        assert!(cache.contains("a") && cache.contains("c"));
+        assert!(!cache.contains("b"));
+        assert!(!cache.observe("b"));
+        assert!(!cache.contains("a"));
+        assert_eq!(cache.len(), 2);
+
+        for _ in 0..100 {
+            cache.observe("c");
+        }
+        assert!(cache.order.len() <= 4);
+
+        let mut disabled = DedupCache::new(0);
+        assert!(!disabled.observe("a"));
+        assert!(!disabled.observe("a"));
+        assert!(disabled.is_empty());
+    }
+
+    #[test]
+    fn memory_store_tiers_accept_single_object() {
+        let single: MemoryStore = serde_json::from_value(serde_json::json!({
+            "scope": "agent",
+            "category": "case",
+            "tiers": { "tier": "l0", "content": "retry with smaller batch" },
+        }))
+        .unwrap();
+        let array: MemoryStore = serde_json::from_value(serde_json::json!({
+            "scope": "agent",
+            "category": "case",
+            "tiers": [{ "tier": "l0", "content": "retry with smaller batch" }],
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&single).unwrap(),
+            serde_json::to_value(&array).unwrap()
+        );
+        assert!(serde_json::to_value(&single).unwrap()["tiers"].is_array());
+    }
+
+    #[test]
+    fn map_fields_serialize_in_key_order() {
+        let command = |keys: &[&str]| KingCommand {
+            command: "reload".into(),
+            target_agent: "a1".into(),
+            params: keys
+                .iter()
+                .map(|k| (k.to_string(), serde_json::json!(k.len())))
+                .collect(),
+        };
+        let forward = serde_json::to_string(&command(&["zeta", "alpha", "mid"])).unwrap();
+        let reverse = serde_json::to_string(&command(&["mid", "alpha", "zeta"])).unwrap();
+        assert_eq!(forward, reverse);
+        assert!(forward.contains(r#"{"alpha":5,"mid":3,"zeta":4}"#));
+    }
+
+    fn task_summary(agent_id: &str, score: Option<f64>, tags: &[&str]) -> TaskSummary {
+        TaskSummary {
+            task_id: "t1".into(),
+            agent_id: agent_id.into(),
+            summary: format!("looks fine to {agent_id}"),
+            score,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            evaluation: serde_json::json!({ agent_id: true, "verdict": agent_id }),
+        }
+    }
+
+    #[test]
+    fn merge_summaries_averages_scores() {
+        let merged = merge_summaries(&[
+            task_summary("eval-1", Some(0.8), &["fast", "correct"]),
+            task_summary("eval-2", Some(0.6), &["correct", "verbose"]),
+            task_summary("eval-3", None, &[]),
+        ])
+        .unwrap();
+        assert!((merged.score.unwrap() - 0.7).abs() < 1e-9);
+        assert_eq!(merged.tags, vec!["fast", "correct", "verbose"]);
+        assert_eq!(merged.agent_id, "eval-1,eval-2,eval-3");
+        assert!(
+            merged
+                .summary
+                .starts_with("[eval-1] looks fine to eval-1\n")
+        );
+        assert_eq!(merged.evaluation["verdict"], "eval-3");
+        assert_eq!(merged.evaluation["eval-1"], true);
+    }
+
+    #[test]
+    fn merge_summaries_rejects_mixed_tasks() {
+        let mut other = task_summary("eval-2", Some(0.6), &[]);
+        other.task_id = "t2".into();
+        let err = merge_summaries(&[task_summary("eval-1", Some(0.8), &[]), other]).unwrap_err();
+        assert_eq!(err.field, "task_id");
+        assert!(merge_summaries(&[]).is_err());
+    }
+
+    #[test]
+    fn memory_record_tier_selection_skips_missing_tiers() {
+        let tier = |tier: &str, content: &str| {
+            serde_json::json!({
+                "id": format!("m1-{tier}"),
+                "memory_id": "m1",
+                "tier": tier,
+                "content": content,
+                "created_at": "2025-01-01T00:00:00Z",
+                "updated_at": "2025-01-01T00:00:00Z",
+            })
+        };
+        let record: MemoryRecord = serde_json::from_value(serde_json::json!({
+            "id": "m1",
+            "scope": "agent",
+            "category": "case",
+            "key": "retry",
+            "tiers": [tier("l2", "full write-up"), tier("l0", "retry smaller")],
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        assert!(record.tier("l1").is_none());
+        assert_eq!(record.most_detailed().unwrap().content, "full write-up");
+        assert_eq!(record.summary_tier().unwrap().content, "retry smaller");
+    }
+
+    #[test]
+    fn pipeline_run_advances_through_stages() {
+        let mut run = PipelineRun::new("run-1");
+        assert_eq!(run.next_stage(), Some(PipelineStage::Learning));
+
+        run.apply(stage_result(PipelineStage::Learning, serde_json::json!({})));
+        assert_eq!(run.status, PipelineRunStatus::Running);
+        assert_eq!(run.next_stage(), Some(PipelineStage::Building));
+
+        let mut building = stage_result(PipelineStage::Building, serde_json::json!({}));
+        building.status = PipelineRunStatus::Failed;
+        run.apply(building);
+        assert_eq!(run.status, PipelineRunStatus::Failed);
+        assert_eq!(run.next_stage(), Some(PipelineStage::Building));
+
+        run.apply(stage_result(PipelineStage::Building, serde_json::json!({})));
+        assert_eq!(run.status, PipelineRunStatus::Running);
+        assert_eq!(run.next_stage(), Some(PipelineStage::PreLoad));
+
+        for stage in &PipelineStage::pipeline_order()[2..] {
+            assert_eq!(run.status, PipelineRunStatus::Running);
+            assert_eq!(run.next_stage().as_ref(), Some(stage));
+            run.apply(stage_result(stage.clone(), serde_json::json!({})));
+        }
+        // SkillManage isn't a pipeline step: Evaluation finishes the run.
+        assert!(run.stages.contains_key(&PipelineStage::Evaluation));
+        assert!(!run.stages.contains_key(&PipelineStage::SkillManage));
+        assert_eq!(run.status, PipelineRunStatus::Completed);
+        assert_eq!(run.next_stage(), None);
+    }
+
+    #[test]
+    fn status_history_counts_flaps() {
+        let mut history = StatusHistory::new(5);
+        let sequence = [
+            RunnerStatus::Ready,
+            RunnerStatus::Ready,
+            RunnerStatus::Error,
+            RunnerStatus::Ready,
+            RunnerStatus::Error,
+            RunnerStatus::Ready,
+        ];
+        for (i, status) in sequence.into_iter().enumerate() {
+            history.record(status, format!("2025-01-01T00:00:0{i}Z"));
+        }
+
+        // The first `Ready` was evicted; the remaining five alternate from the
+        // second entry on.
+        assert_eq!(history.entries().count(), 5);
+        assert_eq!(history.flap_count(5), 4);
+        assert_eq!(history.flap_count(2), 1);
+        assert!(history.is_flapping(4));
+        assert!(!history.is_flapping(5));
+    }
+
+    #[test]
+    fn truncated_delta_respects_char_boundaries() {
+        // "héllo 🦀": 'é' is 2 bytes (1..3), the crab is 4 bytes (7..11).
+        let mut output = task_output(0, "héllo 🦀");
+        assert_eq!(output.truncated_delta(2), "h");
+        assert_eq!(output.truncated_delta(3), "hé");
+        assert_eq!(output.truncated_delta(9), "héllo ");
+        assert_eq!(output.truncated_delta(64), "héllo 🦀");
+
+        output.truncate_delta(10);
+        assert_eq!(output.delta, "héllo ");
+    }
+
+    fn memory(id: &str, relevance: f64, access_count: i64) -> MemoryRecord {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "scope": "agent",
+            "category": "case",
+            "key": id,
+            "relevance_score": relevance,
+            "access_count": access_count,
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn memory_result_rerank_favors_relevant_popular_memories() {
+        let mut result = MemoryResult {
+            memories: vec![
+                memory("recent", 0.4, 1),
+                memory("popular", 0.5, 40),
+                memory("best", 0.9, 30),
+            ],
+            count: 0,
+        };
+        result.rerank(0.7, 0.3);
+        let order: Vec<&str> = result.memories.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(order, vec!["best", "popular", "recent"]);
+        assert_eq!(result.count, 3);
+    }
+
+    #[test]
+    fn task_output_decodes_by_encoding() {
+        let mut output = task_output(0, "\x1b[1;31merror\x1b[0m: \x1b]0;title\x07done");
+        assert!(matches!(output.decoded_text(), Cow::Borrowed(_)));
+        output.encoding = OutputEncoding::Ansi;
+        assert_eq!(output.decoded_text(), "error: done");
+
+        let output: TaskOutput = serde_json::from_value(serde_json::json!({
+            "task_id": "task-001",
+            "request_id": "req-001",
+            "source": "pty",
+            "delta": "aGVsbG8gd29ybGQ=",
+            "chunk_index": 0,
+            "encoding": "base64",
+        }))
+        .unwrap();
+        assert_eq!(output.decoded_text(), "hello world");
+        assert_eq!(task_output(0, "x").encoding, OutputEncoding::default());
+    }
+
+    #[test]
+    fn memory_query_constructors_set_filters() {
+        let task = MemoryQuery::for_task("task-1", "retry strategy");
+        assert_eq!(task.query, "retry strategy");
+        assert_eq!(task.scope, Some(MemoryScope::Task));
+        assert_eq!(task.task_id.as_deref(), Some("task-1"));
+        assert!(task.agent_id.is_none() && task.category.is_none());
+        assert_eq!(task.limit, 20);
+
+        let agent = MemoryQuery::for_agent("learning-001", "sources");
+        assert_eq!(agent.scope, Some(MemoryScope::Agent));
+        assert_eq!(agent.agent_id.as_deref(), Some("learning-001"));
+        assert!(agent.task_id.is_none());
+
+        let category = MemoryQuery::by_category(MemoryCategory::Pattern, "caching");
+        assert!(category.scope.is_none());
+        assert_eq!(category.category, Some(MemoryCategory::Pattern));
+        assert_eq!(category.limit, 20);
+
+        let from_json: MemoryQuery = serde_json::from_str(r#"{"query": ""}"#).unwrap();
+        assert_eq!(from_json.limit, MemoryQuery::default().limit);
+    }
+
+    #[test]
+    fn memory_records_sort_by_each_dimension() {
+        let mut old_popular = memory("old-popular", 0.2, 50);
+        old_popular.updated_at = "2025-01-01T00:00:00Z".into();
+        let mut recent = memory("recent", 0.5, 1);
+        recent.updated_at = "2025-03-01T00:00:00Z".into();
+        let mut relevant = memory("relevant", 0.9, 10);
+        relevant.updated_at = "2025-02-01T00:00:00Z".into();
+        let mut undated = memory("undated", 0.5, 1);
+        undated.updated_at = "yesterday".into();
+        let memories = vec![old_popular, undated, relevant, recent];
+
+        let ids = |sort: MemorySort| {
+            let mut sorted = memories.clone();
+            sort.sort(&mut sorted);
+            sorted.into_iter().map(|m| m.id).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(MemorySort::Relevance),
+            ["relevant", "recent", "undated", "old-popular"]
+        );
+        assert_eq!(
+            ids(MemorySort::Recency),
+            ["recent", "relevant", "old-popular", "undated"]
+        );
+        assert_eq!(
+            ids(MemorySort::Access),
+            ["old-popular", "relevant", "recent", "undated"]
+        );
+
+        let mut by_fn = memories.clone();
+        by_fn.sort_by(MemoryRecord::cmp_by_access);
+        assert_eq!(by_fn[0].id, "old-popular");
+    }
+
+    #[test]
+    fn memory_result_iterates_records() {
+        let empty = MemoryResult::new(vec![]);
+        assert!(empty.is_empty());
+        assert!(empty.first().is_none());
+        assert_eq!(empty.into_iter().count(), 0);
+
+        let result = MemoryResult::new(vec![memory("a", 0.9, 0), memory("b", 0.5, 0)]);
+        assert!(!result.is_empty());
+        assert_eq!(result.count, 2);
+        assert_eq!(result.first().map(|m| m.id.as_str()), Some("a"));
+        let ids: Vec<&str> = result.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        let owned: Vec<MemoryRecord> = result.into_iter().collect();
+        assert_eq!(owned[1].id, "b");
+    }
+
+    #[test]
+    fn skill_stats_rolls_up_per_skill() {
+        let report = |skill_id: &str, result: SkillResult, score: Option<f64>| AgentSkillReport {
+            agent_id: "skill-manage-001".into(),
+            skill_id: skill_id.into(),
+            result,
+            score,
+        };
+        let stats = skill_stats(&[
+            report("search", SkillResult::Success, Some(0.9)),
+            report("search", SkillResult::Failure("timeout".into()), None),
+            report(
+                "summarize",
+                SkillResult::Partial("truncated".into()),
+                Some(0.4),
+            ),
+            report("search", SkillResult::Success, Some(0.7)),
+            report("search", SkillResult::Success, Some(0.8)),
+        ]);
+
+        let search = &stats["search"];
+        assert_eq!(search.runs, 4);
+        assert_eq!(
+            (search.successes, search.failures, search.partials),
+            (3, 1, 0)
+        );
+        assert!((search.pass_rate() - 0.75).abs() < 1e-9);
+        assert!((search.mean_score.unwrap() - 0.8).abs() < 1e-9);
+        assert!(matches!(search.last_result, SkillResult::Success));
+
+        let summarize = &stats["summarize"];
+        assert_eq!(summarize.partials, 1);
+        assert_eq!(summarize.pass_rate(), 0.0);
+        assert_eq!(summarize.mean_score, Some(0.4));
+    }
+
+    #[test]
+    fn enums_accept_kebab_case_aliases() {
+        let status: TaskStatus = serde_json::from_str(r#""in-progress""#).unwrap();
+        assert_eq!(status, TaskStatus::InProgress);
+        assert_eq!(serde_json::to_string(&status).unwrap(), r#""in_progress""#);
+
+        let stage: PipelineStage = serde_json::from_str(r#""skill-manage""#).unwrap();
+        assert_eq!(stage, PipelineStage::SkillManage);
+        let role: AgentRole = serde_json::from_str(r#""pre-load""#).unwrap();
+        assert_eq!(role, AgentRole::PreLoad);
+        assert_eq!("timed-out".parse(), Ok(PipelineRunStatus::TimedOut));
+    }
+
+    #[test]
+    fn event_names_are_unique_and_known() {
+        let mut seen = std::collections::HashSet::new();
+        for name in events::ALL {
+            assert!(seen.insert(*name), "duplicate event name `{name}`");
+        }
+        assert!(events::is_known_event(events::TASK_CREATE));
+        assert!(!events::is_known_event(events::ROOM_KERNEL));
+        assert!(!events::is_known_event("task:crate"));
+    }
+
+    #[test]
+    fn map_macros_match_manual_construction() {
+        let manual = BTreeMap::from([
+            ("queue_depth".to_string(), serde_json::json!(3)),
+            ("ready".to_string(), serde_json::json!(true)),
+            ("mode".to_string(), serde_json::json!("fast")),
+        ]);
+        let status = AgentStatus {
+            agent_id: "a1".into(),
+            status: RunnerStatus::Ready,
+            metrics: crate::metrics! { "queue_depth" => 3, "ready" => true, "mode" => "fast" },
+        };
+        assert_eq!(status.metrics, manual);
+        assert_eq!(crate::params! {}, BTreeMap::new());
+        assert_eq!(crate::params! { "depth" => 2u32 }["depth"], 2);
+    }
+
+    #[test]
+    fn golden_samples_match_wire_format() {
+        let expected = [
+            (
+                "agent:register",
+                r#"{"agent_id":"learning-001","role":"learning","capabilities":["web-search"],"protocol_version":1}"#,
+            ),
+            (
+                "task:create",
+                r#"{"task_type":"summarize","agent_id":"learning-001","payload":{"url":"https://example.com"},"retry_policy":{"max_attempts":3,"backoff":{"kind":"fixed","secs":5}},"priority":"high"}"#,
+            ),
+            (
+                "memory:store",
+                r#"{"scope":"task","category":"case","key":"retry-batch","metadata":{"source":"pty"},"tags":["retry"],"agent_id":"learning-001","run_id":"run-001","skill_id":"","relevance_score":0.5,"tiers":[{"tier":"l0","content":"retry with smaller batch"}],"task_id":"task-001"}"#,
+            ),
+            (
+                "pipeline:stage_result",
+                r#"{"run_id":"run-001","stage":"building","agent_id":"building-001","status":"completed","artifact_id":"artifact-001","output":{"artifact_path":"/skills/web-search"}}"#,
+            ),
+        ];
+
+        let samples = golden_samples();
+        assert_eq!(samples.len(), expected.len());
+        for ((event, actual), (expected_event, expected_json)) in samples.iter().zip(expected) {
+            assert_eq!(*event, expected_event);
+            let expected_json: serde_json::Value = serde_json::from_str(expected_json).unwrap();
+            assert_eq!(actual, &expected_json, "wire format of `{event}` changed");
+            // Samples must also parse back through the event router.
+            Message::from_event(event, actual.clone()).unwrap();
+        }
+    }
+
+    #[cfg(feature = "tracing-otel")]
+    #[test]
+    fn emit_event_injects_traceparent_inside_span() {
+        use opentelemetry::trace::TracerProvider;
+        use tracing_subscriber::prelude::*;
+
+        opentelemetry::global::set_text_map_propagator(
+            opentelemetry_sdk::propagation::TraceContextPropagator::new(),
+        );
+        let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let envelope = tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("handle_task").entered();
+            crate::emit_event!(
+                |envelope: Envelope<TaskGet>| envelope,
+                events::TASK_GET,
+                TaskGet {
+                    task_id: "t1".into()
+                }
+            )
+        });
+        assert!(envelope.trace.contains_key("traceparent"));
+    }
+
+    #[cfg(not(feature = "tracing-otel"))]
+    #[test]
+    fn emit_event_without_otel_leaves_trace_empty() {
+        let envelope = crate::emit_event!(
+            |envelope: Envelope<TaskGet>| envelope,
+            events::TASK_GET,
+            TaskGet {
+                task_id: "t1".into()
+            }
+        );
+        assert!(envelope.trace.is_empty());
+        assert_eq!(envelope.payload.task_id, "t1");
+    }
+
+    #[test]
+    fn task_record_age_and_staleness() {
+        let now = DateTime::parse_from_rfc3339("2026-01-01T02:00:00+00:00")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut recent = task_record("t1", "in_progress", "");
+        recent.updated_at = "2026-01-01T02:30:00+01:00".into();
+        let old = task_record("t2", "in_progress", "");
+
+        assert_eq!(recent.age(now), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(old.age(now), Some(Duration::from_secs(2 * 3600)));
+        assert!(!recent.is_stale(now, Duration::from_secs(3600)));
+        assert!(old.is_stale(now, Duration::from_secs(3600)));
+
+        let mut broken = task_record("t3", "in_progress", "");
+        broken.updated_at = "yesterday".into();
+        assert_eq!(broken.age(now), None);
+        assert!(!broken.is_stale(now, Duration::ZERO));
+    }
+
+    #[test]
+    fn memory_dedup_key_ignores_whitespace_and_case() {
+        let a = memory_store(MemoryScope::Agent, None);
+        let mut b = memory_store(MemoryScope::Agent, None);
+        b.tiers[0].content = "  Retry with\tsmaller   batch \n".into();
+        b.agent_id = "learning-002".into();
+        assert_eq!(a.dedup_key(), b.dedup_key());
+
+        let mut c = memory_store(MemoryScope::Agent, None);
+        c.category = MemoryCategory::Pattern;
+        assert_ne!(a.dedup_key(), c.dedup_key());
+    }
+
+    #[test]
+    fn king_command_ack_round_trip() {
+        let accepted: KingCommandAck = serde_json::from_value(serde_json::json!({
+            "request_id": "req-1",
+            "agent_id": "learning-001",
+            "command": "set_log_level",
+            "status": "accepted",
+        }))
+        .unwrap();
+        assert_eq!(accepted.status, AckStatus::Accepted);
+        assert!(accepted.result.is_null());
+        assert!(accepted.error.is_none());
+
+        let failed = KingCommandAck {
+            status: AckStatus::Failed,
+            error: Some("invalid log filter".into()),
+            ..accepted
+        };
+        let json = serde_json::to_value(&failed).unwrap();
+        assert_eq!(json["status"], "failed");
+        let parsed = Message::from_event(events::KING_COMMAND_ACK, json).unwrap();
+        let Message::KingCommandAck(parsed) = parsed else {
+            panic!("expected KingCommandAck");
+        };
+        assert_eq!(parsed.status, AckStatus::Failed);
+        assert_eq!(parsed.error.as_deref(), Some("invalid log filter"));
+    }
+}
+
+/// Property tests: every generated message must survive a JSON round trip
+/// unchanged (`from_json(to_json(x)) == x`).
+#[cfg(test)]
+mod proptests {
+    use super::*;
+    use proptest::collection::{btree_map, hash_map, vec};
+    use proptest::option;
+    use proptest::prelude::*;
+    use serde::de::DeserializeOwned;
+
+    /// Serialize `msg` to a JSON string, parse it back, and check the result
+    /// equals the original.
+    fn round_trip<T>(msg: &T) -> Result<(), TestCaseError>
+    where
+        T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        let wire = serde_json::to_string(msg)
+            .map_err(|e| TestCaseError::fail(format!("{e} for {msg:?}")))?;
+        let parsed: T = serde_json::from_str(&wire)
+            .map_err(|e| TestCaseError::fail(format!("{e} in {wire}")))?;
+        prop_assert_eq!(&parsed, msg);
+        Ok(())
+    }
+
+    /// Like [`round_trip`], except a message holding a non-finite float
+    /// (`finite == false`) is known not to survive: JSON has no NaN or
+    /// infinity, so serde_json writes `null`, which reads back as an error
+    /// (`f64` fields) or `None` (`Option<f64>` fields). Senders must keep
+    /// these floats finite.
+    fn round_trip_finite<T>(msg: &T, finite: bool) -> Result<(), TestCaseError>
+    where
+        T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        if finite {
+            return round_trip(msg);
+        }
+        let wire = serde_json::to_string(msg)
+            .map_err(|e| TestCaseError::fail(format!("{e} for {msg:?}")))?;
+        let parsed = serde_json::from_str::<T>(&wire).ok();
+        prop_assert!(parsed.as_ref() != Some(msg), "{} round-tripped", wire);
+        Ok(())
+    }
+
+    fn ident() -> impl Strategy<Value = String> {
+        "[a-z0-9_-]{0,12}"
+    }
+
+    /// Finite floats that parse back bit-for-bit; serde_json's default parser
+    /// only guarantees that for short decimals.
+    fn wire_f64() -> impl Strategy<Value = f64> {
+        (-4096i32..=4096).prop_map(|n| f64::from(n) / 1024.0)
+    }
+
+    /// [`wire_f64`] plus NaN and the infinities, which have no JSON form.
+    fn any_f64() -> impl Strategy<Value = f64> {
+        prop_oneof![
+            8 => wire_f64(),
+            1 => Just(f64::NAN),
+            1 => Just(f64::INFINITY),
+            1 => Just(f64::NEG_INFINITY),
+        ]
+    }
+
+    fn json_value() -> impl Strategy<Value = serde_json::Value> {
+        let leaf = prop_oneof![
+            Just(serde_json::Value::Null),
+            any::<bool>().prop_map(serde_json::Value::from),
+            any::<i64>().prop_map(serde_json::Value::from),
+            wire_f64().prop_map(serde_json::Value::from),
+            any::<String>().prop_map(serde_json::Value::from),
+        ];
+        leaf.prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                vec(inner.clone(), 0..4).prop_map(serde_json::Value::Array),
+                btree_map(ident(), inner, 0..4)
+                    .prop_map(|map| serde_json::Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    fn json_map() -> impl Strategy<Value = BTreeMap<String, serde_json::Value>> {
+        btree_map(ident(), json_value(), 0..4)
+    }
+
+    /// Unknown top-level fields; the `x_` prefix keeps them clear of the
+    /// named fields they're flattened next to.
+    fn extensions() -> impl Strategy<Value = HashMap<String, serde_json::Value>> {
+        hash_map("x_[a-z]{1,8}", json_value(), 0..4)
+    }
+
+    macro_rules! arbitrary_unit_enum {
+        ($name:ident { $($variant:ident),+ $(,)? }) => {
+            impl Arbitrary for $name {
+                type Parameters = ();
+                type Strategy = BoxedStrategy<Self>;
+
+                fn arbitrary_with(_: ()) -> Self::Strategy {
+                    prop_oneof![$(Just($name::$variant)),+].boxed()
+                }
+            }
+        };
+    }
+
+    arbitrary_unit_enum!(RunnerStatus {
+        Starting,
+        Ready,
+        Busy,
+        Error,
+        Shutting
+    });
+    arbitrary_unit_enum!(PipelineStage {
+        Learning,
+        Building,
+        PreLoad,
+        Evaluation,
+        SkillManage
+    });
+    arbitrary_unit_enum!(PipelineRunStatus {
+        Running,
+        Completed,
+        Failed,
+        TimedOut
+    });
+    arbitrary_unit_enum!(TaskPriority {
+        Low,
+        Normal,
+        High,
+        Critical
+    });
+    arbitrary_unit_enum!(FailureReason {
+        Timeout,
+        DependencyMissing,
+        Crash,
+        InvalidInput,
+        Other
+    });
+    arbitrary_unit_enum!(AckStatus {
+        Accepted,
+        Completed,
+        Rejected,
+        Failed
+    });
+    arbitrary_unit_enum!(MemoryScope {
+        System,
+        Agent,
+        Pipeline,
+        Skill,
+        Task
+    });
+    arbitrary_unit_enum!(OutputEncoding { Utf8, Ansi, Base64 });
+    arbitrary_unit_enum!(MemoryCategory {
+        Case,
+        Pattern,
+        Fact,
+        Preference,
+        Resource,
+        Event
+    });
+
+    impl Arbitrary for SkillResult {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            prop_oneof![
+                Just(SkillResult::Success),
+                any::<String>().prop_map(SkillResult::Failure),
+                any::<String>().prop_map(SkillResult::Partial),
+            ]
+            .boxed()
+        }
+    }
+
+    impl Arbitrary for Backoff {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            prop_oneof![
+                any::<u64>().prop_map(|secs| Backoff::Fixed { secs }),
+                (any::<u64>(), any_f64(), any::<u64>()).prop_map(
+                    |(base_secs, factor, max_secs)| Backoff::Exponential {
+                        base_secs,
+                        factor,
+                        max_secs,
+                    }
+                ),
+            ]
+            .boxed()
+        }
+    }
+
+    impl Arbitrary for AgentRole {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            prop_oneof![
+                proptest::sample::select(AgentRole::builtins()),
+                ident().prop_map(AgentRole::User),
+            ]
+            .boxed()
+        }
+    }
+
+    impl Arbitrary for AgentRegister {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                ident(),
+                any::<AgentRole>(),
+                vec(ident(), 0..4),
+                any::<Option<u32>>(),
+            )
+                .prop_map(
+                    |(agent_id, role, capabilities, protocol_version)| AgentRegister {
+                        agent_id: AgentId::from(agent_id),
+                        role,
+                        capabilities,
+                        protocol_version,
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for AgentStatus {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (ident(), any::<RunnerStatus>(), json_map())
+                .prop_map(|(agent_id, status, metrics)| AgentStatus {
+                    agent_id: AgentId::from(agent_id),
+                    status,
+                    metrics,
+                })
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for KingCommandAck {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                ident(),
+                ident(),
+                ident(),
+                any::<AckStatus>(),
+                json_value(),
+                option::of(any::<String>()),
+            )
+                .prop_map(
+                    |(request_id, agent_id, command, status, result, error)| KingCommandAck {
+                        request_id,
+                        agent_id,
+                        command,
+                        status,
+                        result,
+                        error,
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for PipelineStageResult {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                ident(),
+                any::<PipelineStage>(),
+                ident(),
+                any::<PipelineRunStatus>(),
+                // `Some("")` travels as `""` and reads back as `None`, like
+                // an absent artifact, so only non-empty ids are generated.
+                option::of("[a-z0-9_-]{1,12}"),
+                json_value(),
+                option::of(any::<String>()),
+                option::of(any::<FailureReason>()),
+            )
+                .prop_map(
+                    |(
+                        run_id,
+                        stage,
+                        agent_id,
+                        status,
+                        artifact_id,
+                        output,
+                        error,
+                        failure_reason,
+                    )| {
+                        PipelineStageResult {
+                            run_id,
+                            stage,
+                            agent_id,
+                            status,
+                            artifact_id,
+                            output,
+                            error,
+                            failure_reason,
+                        }
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for TaskCreate {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            let retry_policy =
+                (any::<u32>(), any::<Backoff>()).prop_map(|(max_attempts, backoff)| RetryPolicy {
+                    max_attempts,
+                    backoff,
+                });
+            (
+                ident(),
+                option::of(ident()),
+                json_value(),
+                option::of(ident()),
+                option::of(retry_policy),
+                any::<TaskPriority>(),
+                extensions(),
+            )
+                .prop_map(
+                    |(
+                        task_type,
+                        agent_id,
+                        payload,
+                        parent_id,
+                        retry_policy,
+                        priority,
+                        extensions,
+                    )| {
+                        TaskCreate {
+                            task_type,
+                            agent_id,
+                            payload,
+                            parent_id,
+                            retry_policy,
+                            priority,
+                            extensions,
+                        }
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for TaskRecord {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                (ident(), ident(), ident(), ident()),
+                json_value(),
+                ident(),
+                any::<TaskPriority>(),
+                (any::<String>(), any::<String>()),
+            )
+                .prop_map(
+                    |(
+                        (id, task_type, status, agent_id),
+                        payload,
+                        parent_id,
+                        priority,
+                        (created_at, updated_at),
+                    )| TaskRecord {
+                        id,
+                        task_type,
+                        status,
+                        agent_id,
+                        payload,
+                        parent_id,
+                        priority,
+                        created_at,
+                        updated_at,
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for TaskOutput {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                ident(),
+                ident(),
+                ident(),
+                any::<String>(),
+                any::<u32>(),
+                any::<bool>(),
+                any::<OutputEncoding>(),
+            )
+                .prop_map(
+                    |(task_id, request_id, source, delta, chunk_index, is_final, encoding)| {
+                        TaskOutput {
+                            task_id,
+                            request_id,
+                            source,
+                            delta,
+                            chunk_index,
+                            is_final,
+                            encoding,
+                        }
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for MemoryStore {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            let tier = (ident(), any::<String>())
+                .prop_map(|(tier, content)| MemoryTierEntry { tier, content });
+            (
+                (any::<MemoryScope>(), any::<MemoryCategory>(), ident()),
+                (json_value(), vec(ident(), 0..4)),
+                (ident(), ident(), ident()),
+                any_f64(),
+                vec(tier, 0..4),
+                option::of(ident()),
+                option::of(any::<u64>()),
+                extensions(),
+            )
+                .prop_map(
+                    |(
+                        (scope, category, key),
+                        (metadata, tags),
+                        (agent_id, run_id, skill_id),
+                        relevance_score,
+                        tiers,
+                        task_id,
+                        ttl_secs,
+                        extensions,
+                    )| MemoryStore {
+                        scope,
+                        category,
+                        key,
+                        metadata,
+                        tags,
+                        agent_id,
+                        run_id,
+                        skill_id,
+                        relevance_score,
+                        tiers,
+                        task_id,
+                        ttl_secs,
+                        extensions,
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for TaskSummary {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                (ident(), ident(), any::<String>()),
+                option::of(any_f64()),
+                vec(ident(), 0..4),
+                json_value(),
+            )
+                .prop_map(
+                    |((task_id, agent_id, summary), score, tags, evaluation)| TaskSummary {
+                        task_id,
+                        agent_id,
+                        summary,
+                        score,
+                        tags,
+                        evaluation,
+                    },
+                )
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for AgentSkillReport {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Self>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (
+                ident(),
+                ident(),
+                any::<SkillResult>(),
+                option::of(any_f64()),
+            )
+                .prop_map(|(agent_id, skill_id, result, score)| AgentSkillReport {
+                    agent_id,
+                    skill_id,
+                    result,
+                    score,
+                })
+                .boxed()
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn agent_register_round_trips(msg in any::<AgentRegister>()) {
+            round_trip(&msg)?;
+        }
+
+        #[test]
+        fn agent_status_round_trips(msg in any::<AgentStatus>()) {
+            round_trip(&msg)?;
+        }
+
+        #[test]
+        fn king_command_ack_round_trips(msg in any::<KingCommandAck>()) {
+            round_trip(&msg)?;
+        }
+
+        #[test]
+        fn pipeline_stage_result_round_trips(msg in any::<PipelineStageResult>()) {
+            round_trip(&msg)?;
+        }
+
+        #[test]
+        fn task_create_round_trips(msg in any::<TaskCreate>()) {
+            let finite = !matches!(
+                msg.retry_policy,
+                Some(RetryPolicy { backoff: Backoff::Exponential { factor, .. }, .. })
+                    if !factor.is_finite()
+            );
+            round_trip_finite(&msg, finite)?;
+        }
+
+        #[test]
+        fn task_record_round_trips(msg in any::<TaskRecord>()) {
+            round_trip(&msg)?;
+        }
+
+        #[test]
+        fn task_output_round_trips(msg in any::<TaskOutput>()) {
+            round_trip(&msg)?;
+        }
+
+        #[test]
+        fn memory_store_round_trips(msg in any::<MemoryStore>()) {
+            round_trip_finite(&msg, msg.relevance_score.is_finite())?;
+        }
+
+        #[test]
+        fn task_summary_round_trips(msg in any::<TaskSummary>()) {
+            round_trip_finite(&msg, msg.score.is_none_or(f64::is_finite))?;
+        }
+
+        #[test]
+        fn agent_skill_report_round_trips(msg in any::<AgentSkillReport>()) {
+            round_trip_finite(&msg, msg.score.is_none_or(f64::is_finite))?;
+        }
+    }
 }
@@ -0,0 +1,64 @@
+//! OpenTelemetry metrics export (counters, histograms) over OTLP HTTP.
+//!
+//! Independent of the `tracing-otel` span export, so a service can enable
+//! metrics without distributed tracing.
+
+use opentelemetry::global;
+use opentelemetry_otlp::{MetricExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+
+/// Shuts down the global meter provider (flushing pending metrics) on drop.
+pub struct MetricsGuard {
+    provider: SdkMeterProvider,
+}
+
+impl Drop for MetricsGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("OpenTelemetry metrics shutdown error: {e}");
+        }
+    }
+}
+
+/// Install a global meter provider exporting to the given OTLP HTTP endpoint
+/// (e.g. `http://localhost:3300`).
+///
+/// `component` becomes the `service.name` resource attribute. Instruments are
+/// then created with `opentelemetry::global::meter(component)`.
+///
+/// The returned guard **must** be held for the process lifetime.
+pub fn init_metrics(component: &str, otlp_endpoint: &str) -> MetricsGuard {
+    // OTLP HTTP metric exporter – the SDK appends `/v1/metrics` automatically
+    let exporter = MetricExporter::builder()
+        .with_http()
+        .with_endpoint(otlp_endpoint)
+        .build()
+        .expect("Failed to build OTLP metric exporter");
+
+    let provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(component.to_owned())
+                .build(),
+        )
+        .build();
+
+    global::set_meter_provider(provider.clone());
+
+    MetricsGuard { provider }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_guard_builds() {
+        let guard = init_metrics("evo-test", "http://127.0.0.1:4318");
+        let counter = global::meter("evo-test").u64_counter("requests").build();
+        counter.add(1, &[]);
+        drop(guard);
+    }
+}
@@ -0,0 +1,33 @@
+//! The types most services need, in one import.
+//!
+//! A curated subset rather than a glob: protocol payloads, the role and
+//! status enums, event names, top-level config and manifest types, and the
+//! logging initializers. Reach into the individual modules for anything else.
+//!
+//! ```
+//! use evo_common::prelude::*;
+//!
+//! let register = AgentRegister {
+//!     agent_id: AgentId::new("learning-001"),
+//!     role: AgentRole::Learning,
+//!     capabilities: vec!["discover".into()],
+//!     protocol_version: None,
+//! };
+//! let message = Message::from_event(events::AGENT_REGISTER, serde_json::to_value(&register)?)?;
+//! assert!(matches!(message, Message::AgentRegister(_)));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+pub use crate::config::{AgentConfig, GatewayConfig, ProviderConfig, ProviderType};
+#[cfg(feature = "tracing-otel")]
+pub use crate::logging::init_logging_with_otel;
+pub use crate::logging::{
+    SamplingLayer, init_logging, init_logging_with_sampling, try_init_logging, with_agent_context,
+};
+pub use crate::messages::{
+    AgentHealth, AgentId, AgentRegister, AgentRole, AgentSkillReport, AgentStatus, Envelope,
+    KingCommand, KingCommandAck, KingConfigUpdate, MemoryQuery, MemoryResult, MemoryStore, Message,
+    PipelineNext, PipelineStage, PipelineStageResult, RunnerStatus, TaskCreate, TaskOutput,
+    TaskRecord, TaskStatus, TaskUpdate, events,
+};
+pub use crate::skill::{SkillConfig, SkillManifest};
@@ -0,0 +1,582 @@
+//! Runtime extension point for providers that live outside this crate.
+//!
+//! A downstream crate implements [`CustomProvider`], registers it in a
+//! [`ProviderRegistry`], and refers to it from config as
+//! `ProviderType::Custom(name)`.
+//!
+//! [`ProviderStats`] and [`ProviderBreakers`] hold observed runtime state
+//! (latency, recent failures) used to pick between configured providers;
+//! [`RateLimiter`] enforces a [`RateLimitConfig`].
+
+use crate::config::{GatewayConfig, ProviderType, RateLimitConfig};
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A provider plugged in at runtime.
+#[async_trait]
+pub trait CustomProvider: Send + Sync {
+    /// Name the provider is registered under; matches `ProviderType::Custom`.
+    fn name(&self) -> &str;
+
+    /// Whether this provider can serve `model`.
+    async fn supports_model(&self, model: &str) -> bool;
+}
+
+/// Custom providers keyed by name.
+#[derive(Default, Clone)]
+pub struct ProviderRegistry {
+    providers: BTreeMap<String, Arc<dyn CustomProvider>>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `provider` under its [`CustomProvider::name`], returning the
+    /// provider it replaced, if any.
+    pub fn register<P: CustomProvider + 'static>(
+        &mut self,
+        provider: P,
+    ) -> Option<Arc<dyn CustomProvider>> {
+        self.providers
+            .insert(provider.name().to_string(), Arc::new(provider))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn CustomProvider>> {
+        self.providers.get(name)
+    }
+
+    /// The registered implementation for a `ProviderType::Custom`; `None` for
+    /// built-in provider types.
+    pub fn for_type(&self, provider_type: &ProviderType) -> Option<&Arc<dyn CustomProvider>> {
+        match provider_type {
+            ProviderType::Custom(name) => self.get(name),
+            _ => None,
+        }
+    }
+
+    /// First provider, in name order, that supports `model`.
+    pub async fn resolve_model(&self, model: &str) -> Option<&Arc<dyn CustomProvider>> {
+        for provider in self.providers.values() {
+            if provider.supports_model(model).await {
+                return Some(provider);
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.providers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
+
+impl std::fmt::Debug for ProviderRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.providers.keys()).finish()
+    }
+}
+
+/// Weight of the newest sample in the latency moving average.
+const DEFAULT_LATENCY_ALPHA: f64 = 0.3;
+
+/// Observed latency per provider, as an exponentially weighted moving
+/// average. Safe to share between request handlers.
+#[derive(Debug)]
+pub struct ProviderStats {
+    alpha: f64,
+    latency_ms: Mutex<HashMap<String, f64>>,
+}
+
+impl Default for ProviderStats {
+    fn default() -> Self {
+        Self::with_alpha(DEFAULT_LATENCY_ALPHA)
+    }
+}
+
+impl ProviderStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `alpha` in `(0, 1]` is the weight given to each new sample; higher
+    /// reacts faster to change.
+    pub fn with_alpha(alpha: f64) -> Self {
+        ProviderStats {
+            alpha: alpha.clamp(f64::EPSILON, 1.0),
+            latency_ms: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_latency(&self, name: &str, ms: u64) {
+        let mut latencies = self.latency_ms.lock().unwrap_or_else(|e| e.into_inner());
+        let sample = ms as f64;
+        latencies
+            .entry(name.to_string())
+            .and_modify(|avg| *avg += self.alpha * (sample - *avg))
+            .or_insert(sample);
+    }
+
+    /// Current moving-average latency for `name`, if any was recorded.
+    pub fn latency_ms(&self, name: &str) -> Option<f64> {
+        let latencies = self.latency_ms.lock().unwrap_or_else(|e| e.into_inner());
+        latencies.get(name).copied()
+    }
+
+    /// Name of the enabled provider listing `model` with the lowest average
+    /// latency. Providers without measurements rank after measured ones, in
+    /// config order; `None` if no enabled provider lists the model.
+    pub fn fastest_for_model<'a>(&self, config: &'a GatewayConfig, model: &str) -> Option<&'a str> {
+        config
+            .providers
+            .iter()
+            .filter(|p| p.enabled && p.models.iter().any(|m| m == model))
+            .min_by(|a, b| {
+                let a = self.latency_ms(&a.name).unwrap_or(f64::INFINITY);
+                let b = self.latency_ms(&b.name).unwrap_or(f64::INFINITY);
+                a.total_cmp(&b)
+            })
+            .map(|p| p.name.as_str())
+    }
+}
+
+/// Phase of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Requests flow normally.
+    Closed,
+    /// Too many consecutive failures; skip the provider until the cooldown
+    /// ends.
+    Open,
+    /// Cooldown over; a single trial request is let through. Its success
+    /// closes the breaker, its failure reopens it for another cooldown.
+    HalfOpen,
+}
+
+/// Consecutive-failure circuit breaker for one provider. Safe to share
+/// between request handlers.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    inner: Mutex<BreakerInner>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    /// When the half-open trial was handed out, if one is in flight.
+    probe_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Open after `failure_threshold` consecutive failures (at least 1) and
+    /// half-open `cooldown` later.
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            inner: Mutex::new(BreakerInner::default()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.lock() = BreakerInner::default();
+    }
+
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now());
+    }
+
+    /// `true` while the provider should be skipped. When half-open, the
+    /// first caller gets `false` and is the trial; everyone else gets `true`
+    /// until that trial is recorded. A trial left unrecorded for a whole
+    /// cooldown is given up on and the next caller becomes the new trial.
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(Instant::now())
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.state_at(Instant::now())
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut inner = self.lock();
+        let half_open = self.phase(&inner, now) == BreakerState::HalfOpen;
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        if half_open || inner.consecutive_failures >= self.failure_threshold {
+            inner.opened_at = Some(now);
+            inner.probe_started = None;
+        }
+    }
+
+    fn is_open_at(&self, now: Instant) -> bool {
+        let mut inner = self.lock();
+        match self.phase(&inner, now) {
+            BreakerState::Closed => false,
+            BreakerState::Open => true,
+            BreakerState::HalfOpen => {
+                let probing = inner
+                    .probe_started
+                    .is_some_and(|started| now.saturating_duration_since(started) < self.cooldown);
+                if !probing {
+                    inner.probe_started = Some(now);
+                }
+                probing
+            }
+        }
+    }
+
+    fn state_at(&self, now: Instant) -> BreakerState {
+        self.phase(&self.lock(), now)
+    }
+
+    fn phase(&self, inner: &BreakerInner, now: Instant) -> BreakerState {
+        match inner.opened_at {
+            None => BreakerState::Closed,
+            Some(opened) if now.saturating_duration_since(opened) >= self.cooldown => {
+                BreakerState::HalfOpen
+            }
+            Some(_) => BreakerState::Open,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A [`CircuitBreaker`] per provider name, created on first use with shared
+/// threshold and cooldown settings.
+#[derive(Debug)]
+pub struct ProviderBreakers {
+    failure_threshold: u32,
+    cooldown: Duration,
+    breakers: Mutex<HashMap<String, Arc<CircuitBreaker>>>,
+}
+
+impl ProviderBreakers {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        ProviderBreakers {
+            failure_threshold,
+            cooldown,
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The breaker for `name`, created closed if it doesn't exist yet.
+    pub fn get(&self, name: &str) -> Arc<CircuitBreaker> {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        breakers
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(CircuitBreaker::new(self.failure_threshold, self.cooldown)))
+            .clone()
+    }
+
+    pub fn record_success(&self, name: &str) {
+        self.get(name).record_success();
+    }
+
+    pub fn record_failure(&self, name: &str) {
+        self.get(name).record_failure();
+    }
+
+    /// [`CircuitBreaker::is_open`] for `name`, so a `false` from a half-open
+    /// breaker makes the caller its trial; unknown providers are closed.
+    pub fn is_open(&self, name: &str) -> bool {
+        let breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        breakers.get(name).is_some_and(|b| b.is_open())
+    }
+}
+
+/// Token bucket enforcing a [`RateLimitConfig`]: holds up to `burst_size`
+/// tokens (at least 1) and refills at
+/// [`refill_per_second`](RateLimitConfig::refill_per_second). Starts full.
+///
+/// Clones share the same bucket, so one limiter can be handed to every
+/// request handler.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_second: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        let capacity = f64::from(config.burst_size.max(1));
+        RateLimiter {
+            capacity,
+            refill_per_second: config.refill_per_second(),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a token if one is available, without waiting.
+    pub fn try_acquire(&self) -> bool {
+        self.acquire_at(Instant::now()).is_ok()
+    }
+
+    /// Take a token, sleeping until one is refilled if necessary. Never
+    /// resolves once the burst is spent if `requests_per_minute` is 0.
+    #[cfg(feature = "tokio")]
+    pub async fn acquire(&self) {
+        loop {
+            match self.acquire_at(Instant::now()) {
+                Ok(()) => return,
+                Err(Some(wait)) => tokio::time::sleep(wait).await,
+                Err(None) => std::future::pending().await,
+            }
+        }
+    }
+
+    /// Take a token as of `now`, or say how long until one is available
+    /// (`None`: never, as nothing refills).
+    fn acquire_at(&self, now: Instant) -> Result<(), Option<Duration>> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.refill_per_second).min(self.capacity);
+        bucket.refilled_at = bucket.refilled_at.max(now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.refill_per_second <= 0.0 {
+            return Err(None);
+        }
+        Err(Some(Duration::from_secs_f64(
+            (1.0 - bucket.tokens) / self.refill_per_second,
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    /// Drives a future that never actually waits.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    struct PrefixProvider {
+        name: &'static str,
+        prefix: &'static str,
+    }
+
+    #[async_trait]
+    impl CustomProvider for PrefixProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn supports_model(&self, model: &str) -> bool {
+            model.starts_with(self.prefix)
+        }
+    }
+
+    #[test]
+    fn resolves_model_through_custom_provider() {
+        let mut registry = ProviderRegistry::new();
+        registry.register(PrefixProvider {
+            name: "acme",
+            prefix: "acme-",
+        });
+
+        let provider = block_on(registry.resolve_model("acme-large")).unwrap();
+        assert_eq!(provider.name(), "acme");
+        assert!(block_on(registry.resolve_model("gpt-4o")).is_none());
+
+        let custom = ProviderType::Custom("acme".into());
+        assert!(registry.for_type(&custom).is_some());
+        assert!(registry.for_type(&ProviderType::Anthropic).is_none());
+    }
+
+    #[test]
+    fn fastest_provider_by_moving_average() {
+        let config: GatewayConfig = serde_json::from_value(serde_json::json!({
+            "server": { "host": "0.0.0.0", "port": 8080 },
+            "providers": [
+                { "name": "slow", "base_url": "http://a", "enabled": true, "models": ["gpt-4o"] },
+                { "name": "fast", "base_url": "http://b", "enabled": true, "models": ["gpt-4o"] },
+                { "name": "other", "base_url": "http://c", "enabled": true, "models": ["llama"] },
+            ],
+        }))
+        .unwrap();
+
+        let stats = ProviderStats::new();
+        assert_eq!(stats.fastest_for_model(&config, "gpt-4o"), Some("slow"));
+        for ms in [900, 1100, 1000] {
+            stats.record_latency("slow", ms);
+        }
+        for ms in [200, 3000, 250] {
+            stats.record_latency("fast", ms);
+        }
+        stats.record_latency("other", 1);
+
+        assert_eq!(stats.fastest_for_model(&config, "gpt-4o"), Some("fast"));
+        assert_eq!(stats.fastest_for_model(&config, "claude"), None);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_half_opens_after_cooldown() {
+        let cooldown = Duration::from_secs(30);
+        let breaker = CircuitBreaker::new(3, cooldown);
+        let start = Instant::now();
+
+        breaker.record_failure_at(start);
+        breaker.record_failure_at(start);
+        assert_eq!(breaker.state_at(start), BreakerState::Closed);
+        breaker.record_failure_at(start);
+        assert_eq!(breaker.state_at(start), BreakerState::Open);
+        assert!(breaker.is_open());
+
+        let after_cooldown = start + cooldown;
+        assert_eq!(breaker.state_at(after_cooldown), BreakerState::HalfOpen);
+        // A failed trial reopens immediately for a fresh cooldown.
+        breaker.record_failure_at(after_cooldown);
+        assert_eq!(breaker.state_at(after_cooldown), BreakerState::Open);
+        assert_eq!(
+            breaker.state_at(after_cooldown + cooldown),
+            BreakerState::HalfOpen
+        );
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn half_open_breaker_admits_a_single_probe() {
+        let cooldown = Duration::from_secs(30);
+        let breaker = CircuitBreaker::new(1, cooldown);
+        let start = Instant::now();
+        breaker.record_failure_at(start);
+
+        let half_open = start + cooldown;
+        assert!(!breaker.is_open_at(half_open));
+        assert!(breaker.is_open_at(half_open));
+        assert!(breaker.is_open_at(half_open + Duration::from_secs(1)));
+
+        // The probe failed: back to open, then one new probe after cooldown.
+        breaker.record_failure_at(half_open + Duration::from_secs(1));
+        let reopened = half_open + Duration::from_secs(1) + cooldown;
+        assert!(!breaker.is_open_at(reopened));
+        assert!(breaker.is_open_at(reopened));
+
+        // An abandoned probe is replaced once it has been out a full cooldown.
+        assert!(!breaker.is_open_at(reopened + cooldown));
+
+        breaker.record_success();
+        assert!(!breaker.is_open_at(reopened + cooldown));
+        assert!(!breaker.is_open_at(reopened + cooldown));
+    }
+
+    #[test]
+    fn provider_breakers_are_independent() {
+        let breakers = ProviderBreakers::new(2, Duration::from_secs(60));
+        breakers.record_failure("openai");
+        breakers.record_failure("anthropic");
+        breakers.record_failure("openai");
+
+        assert!(breakers.is_open("openai"));
+        assert!(!breakers.is_open("anthropic"));
+        assert!(!breakers.is_open("unknown"));
+
+        breakers.record_success("openai");
+        assert!(!breakers.is_open("openai"));
+    }
+
+    fn limiter(requests_per_minute: u32, burst_size: u32) -> RateLimiter {
+        RateLimiter::new(&RateLimitConfig {
+            requests_per_minute,
+            burst_size,
+        })
+    }
+
+    #[test]
+    fn rate_limiter_allows_burst_then_refuses() {
+        fn shareable<T: Clone + Send + Sync>() {}
+        shareable::<RateLimiter>();
+
+        let limiter = limiter(60, 3);
+        let shared = limiter.clone();
+        assert!(limiter.try_acquire());
+        assert!(shared.try_acquire());
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+        assert!(!shared.try_acquire());
+    }
+
+    #[test]
+    fn rate_limiter_refills_over_time() {
+        // One token per second, two at most.
+        let limiter = limiter(60, 2);
+        let start = Instant::now();
+        assert!(limiter.acquire_at(start).is_ok());
+        assert!(limiter.acquire_at(start).is_ok());
+        assert_eq!(limiter.acquire_at(start), Err(Some(Duration::from_secs(1))));
+
+        let half = start + Duration::from_millis(500);
+        assert_eq!(
+            limiter.acquire_at(half),
+            Err(Some(Duration::from_millis(500)))
+        );
+        assert!(limiter.acquire_at(start + Duration::from_secs(1)).is_ok());
+
+        // A long idle period refills only up to the burst size.
+        let later = start + Duration::from_secs(60);
+        assert!(limiter.acquire_at(later).is_ok());
+        assert!(limiter.acquire_at(later).is_ok());
+        assert!(limiter.acquire_at(later).is_err());
+
+        let never = RateLimiter::new(&RateLimitConfig {
+            requests_per_minute: 0,
+            burst_size: 0,
+        });
+        assert!(never.try_acquire());
+        assert_eq!(never.acquire_at(Instant::now()), Err(None));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn rate_limiter_acquire_waits_for_refill() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        // 50 tokens per second: the second acquire waits about 20ms.
+        let limiter = limiter(3000, 1);
+        let start = Instant::now();
+        runtime.block_on(async {
+            limiter.acquire().await;
+            limiter.acquire().await;
+        });
+        assert!(start.elapsed() >= Duration::from_millis(15));
+        assert!(!limiter.try_acquire());
+    }
+}
@@ -0,0 +1,107 @@
+//! Ed25519 signatures over canonical JSON, so agents can verify that a
+//! message (e.g. a `KingCommand`) really came from king.
+
+use crate::canonical::to_canonical_json;
+use ed25519_dalek::{Signer, Verifier};
+use serde::{Deserialize, Serialize};
+
+pub use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+
+/// Sign the canonical JSON encoding of `value`.
+pub fn sign<T: Serialize>(value: &T, key: &SigningKey) -> Result<Signature, serde_json::Error> {
+    Ok(key.sign(to_canonical_json(value)?.as_bytes()))
+}
+
+/// Check `sig` against the canonical JSON encoding of `value`.
+pub fn verify<T: Serialize>(value: &T, sig: &Signature, key: &VerifyingKey) -> bool {
+    match to_canonical_json(value) {
+        Ok(json) => key.verify(json.as_bytes(), sig).is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// A payload plus its hex-encoded Ed25519 signature, ready to send as one
+/// event payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedEnvelope<T> {
+    pub payload: T,
+    pub signature: String,
+}
+
+impl<T: Serialize> SignedEnvelope<T> {
+    pub fn sign(payload: T, key: &SigningKey) -> Result<Self, serde_json::Error> {
+        let signature = sign(&payload, key)?;
+        Ok(Self {
+            payload,
+            signature: to_hex(&signature.to_bytes()),
+        })
+    }
+
+    /// `false` if the signature is malformed or doesn't match the payload.
+    pub fn verify(&self, key: &VerifyingKey) -> bool {
+        from_hex(&self.signature)
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .is_some_and(|bytes| verify(&self.payload, &Signature::from_bytes(&bytes), key))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::KingCommand;
+    use std::collections::BTreeMap;
+
+    fn command() -> KingCommand {
+        KingCommand {
+            command: "discover".into(),
+            target_agent: "learning-001".into(),
+            params: BTreeMap::from([("depth".to_string(), serde_json::json!(2))]),
+        }
+    }
+
+    #[test]
+    fn valid_signature_verifies() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let sig = sign(&command(), &key).unwrap();
+        assert!(verify(&command(), &sig, &key.verifying_key()));
+
+        let envelope = SignedEnvelope::sign(command(), &key).unwrap();
+        let json = serde_json::to_string(&envelope).unwrap();
+        let received: SignedEnvelope<KingCommand> = serde_json::from_str(&json).unwrap();
+        assert!(received.verify(&key.verifying_key()));
+    }
+
+    #[test]
+    fn tampered_payload_fails() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let mut envelope = SignedEnvelope::sign(command(), &key).unwrap();
+        envelope.payload.target_agent = "rogue-001".into();
+        assert!(!envelope.verify(&key.verifying_key()));
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let other = SigningKey::from_bytes(&[9u8; 32]);
+        let envelope = SignedEnvelope::sign(command(), &key).unwrap();
+        assert!(!envelope.verify(&other.verifying_key()));
+
+        let mut garbled = envelope.clone();
+        garbled.signature.truncate(10);
+        assert!(!garbled.verify(&key.verifying_key()));
+    }
+}
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "toml")]
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillManifest {
@@ -42,8 +45,66 @@ pub struct SkillEndpoint {
     pub headers: HashMap<String, String>,
 }
 
+impl SkillEndpoint {
+    /// Substitute `{name}` placeholders in `url` with percent-encoded values
+    /// from `params`. Every placeholder must be filled; unused params are
+    /// ignored.
+    pub fn expand_url(&self, params: &HashMap<String, String>) -> Result<String, UrlTemplateError> {
+        let mut expanded = String::with_capacity(self.url.len());
+        let mut rest = self.url.as_str();
+        while let Some(start) = rest.find('{') {
+            expanded.push_str(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                return Err(UrlTemplateError::Unclosed(rest[start..].to_string()));
+            };
+            let name = &rest[start + 1..start + len];
+            let value = params
+                .get(name)
+                .ok_or_else(|| UrlTemplateError::MissingParam(name.to_string()))?;
+            percent_encode_into(value, &mut expanded);
+            rest = &rest[start + len + 1..];
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+}
+
+/// Encode everything except RFC 3986 unreserved characters.
+fn percent_encode_into(value: &str, out: &mut String) {
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+}
+
+/// Why [`SkillEndpoint::expand_url`] couldn't fill in a URL template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlTemplateError {
+    /// No value was given for this placeholder.
+    MissingParam(String),
+    /// A `{` with no closing `}`; holds the rest of the template from it.
+    Unclosed(String),
+}
+
+impl fmt::Display for UrlTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrlTemplateError::MissingParam(name) => {
+                write!(f, "no value for URL placeholder `{{{name}}}`")
+            }
+            UrlTemplateError::Unclosed(rest) => write!(f, "unclosed URL placeholder at `{rest}`"),
+        }
+    }
+}
+
+impl std::error::Error for UrlTemplateError {}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "UPPERCASE")]
+#[non_exhaustive]
 pub enum HttpMethod {
     Get,
     Post,
@@ -53,15 +114,429 @@ pub enum HttpMethod {
 }
 
 impl SkillManifest {
+    #[cfg(feature = "toml")]
     pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
         toml::from_str(content)
     }
+
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+
+    /// Stable content hash of the manifest (see [`crate::config::hash_config`]).
+    pub fn content_hash(&self) -> Result<String, serde_json::Error> {
+        crate::config::hash_config(self)
+    }
+
+    /// JSON Schema (object) describing the payload this skill accepts, built
+    /// from `inputs`. Inputs whose `type` isn't a JSON Schema type are left
+    /// unconstrained.
+    pub fn input_schema(&self) -> serde_json::Value {
+        let properties: serde_json::Map<String, serde_json::Value> = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let mut property = serde_json::Map::new();
+                if JSON_TYPES.contains(&input.r#type.as_str()) {
+                    property.insert("type".into(), input.r#type.clone().into());
+                }
+                if let Some(description) = &input.description {
+                    property.insert("description".into(), description.clone().into());
+                }
+                (input.name.clone(), property.into())
+            })
+            .collect();
+        let required: Vec<&str> = self
+            .inputs
+            .iter()
+            .filter(|input| input.required)
+            .map(|input| input.name.as_str())
+            .collect();
+        serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+
+    /// Check `payload` has every required input and that present inputs have
+    /// their declared type. Returns one message per problem.
+    pub fn validate_payload(&self, payload: &serde_json::Value) -> Result<(), Vec<String>> {
+        let Some(fields) = payload.as_object() else {
+            return Err(vec!["payload must be a JSON object".into()]);
+        };
+        let mut errors = Vec::new();
+        for input in &self.inputs {
+            match fields.get(&input.name) {
+                None | Some(serde_json::Value::Null) if input.required => {
+                    errors.push(format!("missing required input `{}`", input.name));
+                }
+                Some(value) if !value.is_null() && !matches_json_type(&input.r#type, value) => {
+                    errors.push(format!(
+                        "input `{}` should be of type `{}`",
+                        input.name, input.r#type
+                    ));
+                }
+                _ => {}
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// `SkillIO::type` values understood by [`SkillManifest::input_schema`].
+const JSON_TYPES: &[&str] = &["string", "number", "integer", "boolean", "array", "object"];
+
+/// Whether `value` has JSON Schema type `ty`; unknown types accept anything.
+fn matches_json_type(ty: &str, value: &serde_json::Value) -> bool {
+    match ty {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => true,
+    }
 }
 
 impl SkillConfig {
+    #[cfg(feature = "toml")]
     pub fn from_toml(content: &str) -> Result<Self, toml::de::Error> {
         toml::from_str(content)
     }
+
+    pub fn from_json(content: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(content)
+    }
+}
+
+/// Capabilities a skill advertises without an endpoint to back them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindingError {
+    pub skill: String,
+    pub unbound: Vec<String>,
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "skill `{}` has no endpoint for capabilities: {}",
+            self.skill,
+            self.unbound.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for BindingError {}
+
+/// Check every capability in `manifest` is backed by an endpoint in `config`.
+///
+/// Convention: a capability is bound by the endpoint whose `name` is exactly
+/// the capability name. Endpoints without a matching capability are allowed
+/// (e.g. internal helpers).
+pub fn check_bindings(manifest: &SkillManifest, config: &SkillConfig) -> Result<(), BindingError> {
+    let unbound: Vec<String> = manifest
+        .capabilities
+        .iter()
+        .filter(|capability| !config.endpoints.iter().any(|e| &e.name == *capability))
+        .cloned()
+        .collect();
+    if unbound.is_empty() {
+        return Ok(());
+    }
+    Err(BindingError {
+        skill: manifest.name.clone(),
+        unbound,
+    })
+}
+
+/// Load the skill in `dir`: `manifest.toml` (required) and `config.toml`
+/// (optional, `None` if absent). When both exist, the config must bind
+/// every capability (see [`check_bindings`]).
+#[cfg(feature = "toml")]
+pub fn load_skill(
+    dir: impl AsRef<Path>,
+) -> Result<(SkillManifest, Option<SkillConfig>), SkillError> {
+    let dir = dir.as_ref();
+    let manifest_path = dir.join("manifest.toml");
+    let manifest = match std::fs::read_to_string(&manifest_path) {
+        Ok(content) => SkillManifest::from_toml(&content).map_err(|source| SkillError::Parse {
+            path: manifest_path,
+            source,
+        })?,
+        Err(source) => {
+            return Err(SkillError::Read {
+                path: manifest_path,
+                source,
+            });
+        }
+    };
+
+    let config_path = dir.join("config.toml");
+    let config = match std::fs::read_to_string(&config_path) {
+        Ok(content) => SkillConfig::from_toml(&content).map_err(|source| SkillError::Parse {
+            path: config_path,
+            source,
+        })?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((manifest, None)),
+        Err(source) => {
+            return Err(SkillError::Read {
+                path: config_path,
+                source,
+            });
+        }
+    };
+    check_bindings(&manifest, &config).map_err(SkillError::Binding)?;
+    Ok((manifest, Some(config)))
+}
+
+/// Why [`load_skill`] couldn't load a skill directory.
+#[cfg(feature = "toml")]
+#[derive(Debug)]
+pub enum SkillError {
+    /// The file couldn't be read; for `manifest.toml` this includes it not
+    /// existing.
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The file isn't a valid manifest or config.
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// The config doesn't bind every capability the manifest advertises.
+    Binding(BindingError),
+}
+
+#[cfg(feature = "toml")]
+impl fmt::Display for SkillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkillError::Read { path, source } => {
+                write!(f, "cannot read {}: {source}", path.display())
+            }
+            SkillError::Parse { path, source } => {
+                write!(f, "invalid {}: {source}", path.display())
+            }
+            SkillError::Binding(e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl std::error::Error for SkillError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SkillError::Read { source, .. } => Some(source),
+            SkillError::Parse { source, .. } => Some(source),
+            SkillError::Binding(e) => Some(e),
+        }
+    }
+}
+
+/// Why a set of manifests can't be loaded together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyError {
+    /// Skills forming a cycle, starting and ending with the same name.
+    Cycle(Vec<String>),
+    /// `skill` depends on `dependency`, which isn't in the set.
+    Missing { skill: String, dependency: String },
+}
+
+impl fmt::Display for DependencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyError::Cycle(path) => {
+                write!(f, "dependency cycle: {}", path.join(" -> "))
+            }
+            DependencyError::Missing { skill, dependency } => {
+                write!(f, "skill `{skill}` depends on unknown skill `{dependency}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DependencyError {}
+
+/// Check that every dependency exists and there are no cycles, returning the
+/// skill names in load order (dependencies before dependents).
+///
+/// The order is deterministic: it follows the input order wherever the
+/// dependency constraints allow.
+pub fn validate_dependency_graph(
+    manifests: &[SkillManifest],
+) -> Result<Vec<String>, DependencyError> {
+    let by_name: HashMap<&str, &SkillManifest> =
+        manifests.iter().map(|m| (m.name.as_str(), m)).collect();
+
+    for manifest in manifests {
+        if let Some(dep) = manifest
+            .dependencies
+            .iter()
+            .find(|dep| !by_name.contains_key(dep.as_str()))
+        {
+            return Err(DependencyError::Missing {
+                skill: manifest.name.clone(),
+                dependency: dep.clone(),
+            });
+        }
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        name: &'a str,
+        by_name: &HashMap<&'a str, &'a SkillManifest>,
+        marks: &mut HashMap<&'a str, Mark>,
+        path: &mut Vec<&'a str>,
+        order: &mut Vec<String>,
+    ) -> Result<(), DependencyError> {
+        match marks.get(name) {
+            Some(Mark::Done) => return Ok(()),
+            Some(Mark::Visiting) => {
+                let start = path.iter().position(|n| *n == name).unwrap_or(0);
+                let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+                cycle.push(name.to_string());
+                return Err(DependencyError::Cycle(cycle));
+            }
+            None => {}
+        }
+        marks.insert(name, Mark::Visiting);
+        path.push(name);
+        for dep in &by_name[name].dependencies {
+            visit(dep.as_str(), by_name, marks, path, order)?;
+        }
+        path.pop();
+        marks.insert(name, Mark::Done);
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    let mut marks = HashMap::new();
+    let mut order = Vec::with_capacity(manifests.len());
+    for manifest in manifests {
+        visit(
+            &manifest.name,
+            &by_name,
+            &mut marks,
+            &mut Vec::new(),
+            &mut order,
+        )?;
+    }
+    Ok(order)
+}
+
+/// How a manifest update affects existing callers, from least to most
+/// disruptive. See [`compatibility`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Compatibility {
+    /// Existing callers see the same interface; at most optional inputs were
+    /// added.
+    Compatible,
+    /// Additive or relaxing changes: new outputs or capabilities, inputs made
+    /// optional or optional inputs dropped.
+    MinorChange,
+    /// Existing callers may fail: see [`compatibility`].
+    Breaking,
+}
+
+/// Classify the update from `old` to `new` by comparing inputs, outputs and
+/// capabilities by name.
+///
+/// `Breaking` if a required input or any output or capability was removed,
+/// a required input was added, an optional input became required, an input
+/// or output changed `type`, or the semver major version changed (for `0.x`
+/// versions, the minor). Unparseable versions are ignored.
+pub fn compatibility(old: &SkillManifest, new: &SkillManifest) -> Compatibility {
+    let mut level = Compatibility::Compatible;
+    let mut raise = |to: Compatibility| level = level.max(to);
+
+    if let (Some(old_version), Some(new_version)) = (
+        breaking_version(&old.version),
+        breaking_version(&new.version),
+    ) && old_version != new_version
+    {
+        raise(Compatibility::Breaking);
+    }
+
+    for old_input in &old.inputs {
+        match new.inputs.iter().find(|i| i.name == old_input.name) {
+            None if old_input.required => raise(Compatibility::Breaking),
+            None => raise(Compatibility::MinorChange),
+            Some(new_input) if new_input.r#type != old_input.r#type => {
+                raise(Compatibility::Breaking)
+            }
+            Some(new_input) if new_input.required && !old_input.required => {
+                raise(Compatibility::Breaking)
+            }
+            Some(new_input) if !new_input.required && old_input.required => {
+                raise(Compatibility::MinorChange)
+            }
+            Some(_) => {}
+        }
+    }
+    if new
+        .inputs
+        .iter()
+        .any(|i| i.required && !old.inputs.iter().any(|o| o.name == i.name))
+    {
+        raise(Compatibility::Breaking);
+    }
+
+    for old_output in &old.outputs {
+        match new.outputs.iter().find(|o| o.name == old_output.name) {
+            Some(new_output) if new_output.r#type == old_output.r#type => {}
+            _ => raise(Compatibility::Breaking),
+        }
+    }
+    if new
+        .outputs
+        .iter()
+        .any(|o| !old.outputs.iter().any(|old| old.name == o.name))
+    {
+        raise(Compatibility::MinorChange);
+    }
+
+    if old
+        .capabilities
+        .iter()
+        .any(|c| !new.capabilities.contains(c))
+    {
+        raise(Compatibility::Breaking);
+    }
+    if new
+        .capabilities
+        .iter()
+        .any(|c| !old.capabilities.contains(c))
+    {
+        raise(Compatibility::MinorChange);
+    }
+
+    level
+}
+
+/// The version components whose change is breaking under semver: the major,
+/// or `(0, minor)` before 1.0. Accepts an optional leading `v`.
+fn breaking_version(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major: u64 = parts.next()?.parse().ok()?;
+    if major > 0 {
+        return Some((major, 0));
+    }
+    let minor = parts.next().map_or(Some(0), |minor| minor.parse().ok())?;
+    Some((0, minor))
 }
 
 #[cfg(test)]
@@ -69,6 +544,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "toml")]
     fn parse_skill_manifest() {
         let toml_str = r#"
 name = "web-search"
@@ -97,6 +573,7 @@ description = "Search results"
     }
 
     #[test]
+    #[cfg(feature = "toml")]
     fn parse_skill_config() {
         let toml_str = r#"
 auth_ref = "SEARCH_API_KEY"
@@ -113,4 +590,336 @@ Accept = "application/json"
         assert_eq!(config.endpoints[0].method, HttpMethod::Get);
         assert_eq!(config.auth_ref.unwrap(), "SEARCH_API_KEY");
     }
+
+    #[cfg(feature = "toml")]
+    fn skill_dir(name: &str, manifest: Option<&str>, config: Option<&str>) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("evo-common-skill-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        if let Some(manifest) = manifest {
+            std::fs::write(dir.join("manifest.toml"), manifest).unwrap();
+        }
+        if let Some(config) = config {
+            std::fs::write(dir.join("config.toml"), config).unwrap();
+        }
+        dir
+    }
+
+    #[cfg(feature = "toml")]
+    const SEARCH_MANIFEST: &str = r#"
+name = "web-search"
+version = "0.1.0"
+description = "Search the web"
+capabilities = ["search"]
+inputs = []
+outputs = []
+"#;
+
+    #[cfg(feature = "toml")]
+    const SEARCH_CONFIG: &str = r#"
+[[endpoints]]
+name = "search"
+url = "https://api.search.com/v1/search"
+method = "GET"
+"#;
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn load_skill_with_manifest_and_config() {
+        let dir = skill_dir("both", Some(SEARCH_MANIFEST), Some(SEARCH_CONFIG));
+        let (manifest, config) = load_skill(&dir).unwrap();
+        assert_eq!(manifest.name, "web-search");
+        assert_eq!(config.unwrap().endpoints[0].name, "search");
+
+        std::fs::write(
+            dir.join("config.toml"),
+            SEARCH_CONFIG.replace("\"search\"", "\"lookup\""),
+        )
+        .unwrap();
+        let err = load_skill(&dir).unwrap_err();
+        assert!(matches!(&err, SkillError::Binding(e) if e.unbound == ["search"]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn load_skill_without_config() {
+        let dir = skill_dir("manifest-only", Some(SEARCH_MANIFEST), None);
+        let (manifest, config) = load_skill(&dir).unwrap();
+        assert_eq!(manifest.capabilities, ["search"]);
+        assert!(config.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn load_skill_requires_manifest() {
+        let dir = skill_dir("no-manifest", None, Some(SEARCH_CONFIG));
+        let err = load_skill(&dir).unwrap_err();
+        assert!(matches!(&err, SkillError::Read { path, source }
+                if *path == dir.join("manifest.toml")
+                    && source.kind() == std::io::ErrorKind::NotFound));
+        assert!(err.to_string().contains("manifest.toml"));
+
+        std::fs::write(dir.join("manifest.toml"), "name = ").unwrap();
+        assert!(matches!(
+            load_skill(&dir),
+            Err(SkillError::Parse { path, .. }) if path == dir.join("manifest.toml")
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn manifest_with_deps(name: &str, deps: &[&str]) -> SkillManifest {
+        SkillManifest {
+            name: name.into(),
+            version: "0.1.0".into(),
+            description: String::new(),
+            capabilities: vec![],
+            inputs: vec![],
+            outputs: vec![],
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            has_code: false,
+        }
+    }
+
+    fn io(name: &str, ty: &str, required: bool) -> SkillIO {
+        SkillIO {
+            name: name.into(),
+            r#type: ty.into(),
+            required,
+            description: None,
+        }
+    }
+
+    fn compat_manifest() -> SkillManifest {
+        SkillManifest {
+            version: "1.2.0".into(),
+            capabilities: vec!["search".into()],
+            inputs: vec![io("query", "string", true), io("limit", "integer", false)],
+            outputs: vec![io("results", "array", true)],
+            ..manifest_with_deps("web-search", &[])
+        }
+    }
+
+    #[test]
+    fn compatibility_compatible_changes() {
+        let old = compat_manifest();
+        assert_eq!(compatibility(&old, &old), Compatibility::Compatible);
+
+        let mut new = compat_manifest();
+        new.version = "1.3.0".into();
+        new.description = "Search the web".into();
+        new.inputs.push(io("region", "string", false));
+        assert_eq!(compatibility(&old, &new), Compatibility::Compatible);
+    }
+
+    #[test]
+    fn compatibility_minor_changes() {
+        let old = compat_manifest();
+
+        let mut new = compat_manifest();
+        new.outputs.push(io("total", "integer", false));
+        assert_eq!(compatibility(&old, &new), Compatibility::MinorChange);
+
+        let mut new = compat_manifest();
+        new.inputs.retain(|i| i.name != "limit");
+        assert_eq!(compatibility(&old, &new), Compatibility::MinorChange);
+
+        let mut new = compat_manifest();
+        new.inputs[0].required = false;
+        assert_eq!(compatibility(&old, &new), Compatibility::MinorChange);
+    }
+
+    #[test]
+    fn compatibility_breaking_changes() {
+        let old = compat_manifest();
+        let breaking = |change: fn(&mut SkillManifest)| {
+            let mut new = compat_manifest();
+            change(&mut new);
+            compatibility(&old, &new)
+        };
+
+        assert_eq!(
+            breaking(|m| m.inputs.retain(|i| i.name != "query")),
+            Compatibility::Breaking
+        );
+        assert_eq!(breaking(|m| m.outputs.clear()), Compatibility::Breaking);
+        assert_eq!(
+            breaking(|m| m.inputs.push(io("api_key", "string", true))),
+            Compatibility::Breaking
+        );
+        assert_eq!(
+            breaking(|m| m.inputs[1].required = true),
+            Compatibility::Breaking
+        );
+        assert_eq!(
+            breaking(|m| m.outputs[0].r#type = "object".into()),
+            Compatibility::Breaking
+        );
+        assert_eq!(
+            breaking(|m| m.capabilities.clear()),
+            Compatibility::Breaking
+        );
+        assert_eq!(
+            breaking(|m| m.version = "2.0.0".into()),
+            Compatibility::Breaking
+        );
+    }
+
+    #[test]
+    fn compatibility_pre_1_0_minor_bump_is_breaking() {
+        let old = manifest_with_deps("fetch", &[]);
+        let mut new = old.clone();
+        new.version = "0.1.7".into();
+        assert_eq!(compatibility(&old, &new), Compatibility::Compatible);
+        new.version = "0.2.0".into();
+        assert_eq!(compatibility(&old, &new), Compatibility::Breaking);
+        new.version = "latest".into();
+        assert_eq!(compatibility(&old, &new), Compatibility::Compatible);
+    }
+
+    #[test]
+    fn dependency_graph_valid_dag() {
+        let manifests = vec![
+            manifest_with_deps("summarize", &["web-search", "fetch"]),
+            manifest_with_deps("web-search", &["fetch"]),
+            manifest_with_deps("fetch", &[]),
+        ];
+        let order = validate_dependency_graph(&manifests).unwrap();
+        assert_eq!(order, vec!["fetch", "web-search", "summarize"]);
+    }
+
+    #[test]
+    fn dependency_graph_detects_cycle() {
+        let manifests = vec![
+            manifest_with_deps("a", &["b"]),
+            manifest_with_deps("b", &["c"]),
+            manifest_with_deps("c", &["a"]),
+        ];
+        let err = validate_dependency_graph(&manifests).unwrap_err();
+        assert_eq!(
+            err,
+            DependencyError::Cycle(vec!["a".into(), "b".into(), "c".into(), "a".into()])
+        );
+        assert_eq!(err.to_string(), "dependency cycle: a -> b -> c -> a");
+    }
+
+    #[test]
+    fn dependency_graph_reports_missing_dependency() {
+        let manifests = vec![manifest_with_deps("summarize", &["web-search"])];
+        let err = validate_dependency_graph(&manifests).unwrap_err();
+        assert_eq!(
+            err,
+            DependencyError::Missing {
+                skill: "summarize".into(),
+                dependency: "web-search".into(),
+            }
+        );
+    }
+
+    fn search_manifest() -> SkillManifest {
+        let input = |name: &str, ty: &str, required: bool| SkillIO {
+            name: name.into(),
+            r#type: ty.into(),
+            required,
+            description: None,
+        };
+        SkillManifest {
+            inputs: vec![
+                input("query", "string", true),
+                input("limit", "integer", false),
+            ],
+            ..manifest_with_deps("web-search", &[])
+        }
+    }
+
+    #[test]
+    fn input_schema_lists_properties_and_required() {
+        let schema = search_manifest().input_schema();
+        assert_eq!(schema["properties"]["query"]["type"], "string");
+        assert_eq!(schema["properties"]["limit"]["type"], "integer");
+        assert_eq!(schema["required"], serde_json::json!(["query"]));
+    }
+
+    #[test]
+    fn validate_payload_checks_required_and_types() {
+        let manifest = search_manifest();
+        assert!(
+            manifest
+                .validate_payload(&serde_json::json!({ "query": "rust", "limit": 5 }))
+                .is_ok()
+        );
+
+        let errors = manifest
+            .validate_payload(&serde_json::json!({ "limit": "five" }))
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].contains("`query`"));
+        assert!(errors[1].contains("`integer`"));
+    }
+
+    fn endpoint(url: &str) -> SkillEndpoint {
+        SkillEndpoint {
+            name: "get-item".into(),
+            url: url.into(),
+            method: HttpMethod::Get,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn expand_url_fills_placeholders() {
+        let params = HashMap::from([
+            ("id".to_string(), "42".to_string()),
+            ("field".to_string(), "name".to_string()),
+        ]);
+        let url = endpoint("https://api.x.com/v1/items/{id}/{field}")
+            .expand_url(&params)
+            .unwrap();
+        assert_eq!(url, "https://api.x.com/v1/items/42/name");
+    }
+
+    #[test]
+    fn expand_url_errors_on_missing_param() {
+        let err = endpoint("https://api.x.com/v1/items/{id}")
+            .expand_url(&HashMap::new())
+            .unwrap_err();
+        assert_eq!(err, UrlTemplateError::MissingParam("id".into()));
+        assert_eq!(err.to_string(), "no value for URL placeholder `{id}`");
+    }
+
+    #[test]
+    fn expand_url_percent_encodes_values() {
+        let params = HashMap::from([("q".to_string(), "rust lang/é".to_string())]);
+        let url = endpoint("https://api.x.com/search/{q}")
+            .expand_url(&params)
+            .unwrap();
+        assert_eq!(url, "https://api.x.com/search/rust%20lang%2F%C3%A9");
+    }
+
+    #[test]
+    fn check_bindings_reports_unbound_capabilities() {
+        let manifest = SkillManifest {
+            capabilities: vec!["search".into(), "summarize".into()],
+            ..manifest_with_deps("web-search", &[])
+        };
+        let mut config = SkillConfig {
+            endpoints: vec![SkillEndpoint {
+                name: "search".into(),
+                ..endpoint("https://api.x.com/search")
+            }],
+            auth_ref: None,
+            extra: HashMap::new(),
+        };
+
+        let err = check_bindings(&manifest, &config).unwrap_err();
+        assert_eq!(err.unbound, vec!["summarize".to_string()]);
+
+        config.endpoints.push(SkillEndpoint {
+            name: "summarize".into(),
+            ..endpoint("https://api.x.com/summarize")
+        });
+        assert!(check_bindings(&manifest, &config).is_ok());
+    }
 }
@@ -0,0 +1,182 @@
+//! Compact binary encoding of [`MemoryRecord`]s for embedded key-value
+//! stores, where JSON is too large and too slow to parse.
+//!
+//! Encoded records start with a format version byte followed by a bincode
+//! payload. Decoding rejects versions it doesn't know, so the layout can
+//! change without misreading old or newer data.
+
+use crate::messages::{MemoryRecord, MemoryTierRecord};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Version byte written by [`memory_to_bytes`].
+pub const MEMORY_FORMAT_VERSION: u8 = 1;
+
+/// Version 1 layout. bincode can't encode a free-form `serde_json::Value`,
+/// so `metadata` is stored as its JSON text.
+#[derive(Serialize, Deserialize)]
+struct StoredMemoryV1<'a> {
+    id: Cow<'a, str>,
+    scope: Cow<'a, str>,
+    category: Cow<'a, str>,
+    key: Cow<'a, str>,
+    tiers: Cow<'a, [MemoryTierRecord]>,
+    metadata: String,
+    tags: Cow<'a, [String]>,
+    agent_id: Cow<'a, str>,
+    run_id: Cow<'a, str>,
+    skill_id: Cow<'a, str>,
+    relevance_score: f64,
+    access_count: i64,
+    created_at: Cow<'a, str>,
+    updated_at: Cow<'a, str>,
+}
+
+/// Encode `memory` as a version byte followed by its bincode form.
+pub fn memory_to_bytes(memory: &MemoryRecord) -> Vec<u8> {
+    let stored = StoredMemoryV1 {
+        id: Cow::Borrowed(&memory.id),
+        scope: Cow::Borrowed(&memory.scope),
+        category: Cow::Borrowed(&memory.category),
+        key: Cow::Borrowed(&memory.key),
+        tiers: Cow::Borrowed(&memory.tiers),
+        metadata: memory.metadata.to_string(),
+        tags: Cow::Borrowed(&memory.tags),
+        agent_id: Cow::Borrowed(&memory.agent_id),
+        run_id: Cow::Borrowed(&memory.run_id),
+        skill_id: Cow::Borrowed(&memory.skill_id),
+        relevance_score: memory.relevance_score,
+        access_count: memory.access_count,
+        created_at: Cow::Borrowed(&memory.created_at),
+        updated_at: Cow::Borrowed(&memory.updated_at),
+    };
+    let mut bytes = vec![MEMORY_FORMAT_VERSION];
+    // Writing plain strings and numbers into a Vec has no failure mode.
+    bincode::serialize_into(&mut bytes, &stored).expect("bincode encoding into a Vec");
+    bytes
+}
+
+/// Decode bytes produced by [`memory_to_bytes`].
+pub fn memory_from_bytes(bytes: &[u8]) -> Result<MemoryRecord, MemoryCodecError> {
+    let (&version, payload) = bytes.split_first().ok_or(MemoryCodecError::Empty)?;
+    if version != MEMORY_FORMAT_VERSION {
+        return Err(MemoryCodecError::UnsupportedVersion(version));
+    }
+    let stored: StoredMemoryV1 = bincode::deserialize(payload).map_err(MemoryCodecError::Decode)?;
+    Ok(MemoryRecord {
+        id: stored.id.into_owned(),
+        scope: stored.scope.into_owned(),
+        category: stored.category.into_owned(),
+        key: stored.key.into_owned(),
+        tiers: stored.tiers.into_owned(),
+        metadata: serde_json::from_str(&stored.metadata).map_err(MemoryCodecError::Metadata)?,
+        tags: stored.tags.into_owned(),
+        agent_id: stored.agent_id.into_owned(),
+        run_id: stored.run_id.into_owned(),
+        skill_id: stored.skill_id.into_owned(),
+        relevance_score: stored.relevance_score,
+        access_count: stored.access_count,
+        created_at: stored.created_at.into_owned(),
+        updated_at: stored.updated_at.into_owned(),
+    })
+}
+
+/// Why [`memory_from_bytes`] failed.
+#[derive(Debug)]
+pub enum MemoryCodecError {
+    /// No version byte.
+    Empty,
+    /// Written by a format version this build doesn't understand.
+    UnsupportedVersion(u8),
+    /// The payload isn't a valid record for its version.
+    Decode(bincode::Error),
+    /// The stored `metadata` isn't valid JSON.
+    Metadata(serde_json::Error),
+}
+
+impl fmt::Display for MemoryCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryCodecError::Empty => f.write_str("encoded memory record is empty"),
+            MemoryCodecError::UnsupportedVersion(v) => write!(
+                f,
+                "unsupported memory record format version {v} (expected {MEMORY_FORMAT_VERSION})"
+            ),
+            MemoryCodecError::Decode(e) => write!(f, "invalid memory record: {e}"),
+            MemoryCodecError::Metadata(e) => write!(f, "invalid memory record metadata: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MemoryCodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MemoryCodecError::Empty | MemoryCodecError::UnsupportedVersion(_) => None,
+            MemoryCodecError::Decode(e) => Some(e),
+            MemoryCodecError::Metadata(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory() -> MemoryRecord {
+        serde_json::from_value(serde_json::json!({
+            "id": "mem-1",
+            "scope": "agent",
+            "category": "pattern",
+            "key": "retry-backoff",
+            "tiers": [{
+                "id": "tier-1",
+                "memory_id": "mem-1",
+                "tier": "l0",
+                "content": "exponential backoff for 429s",
+                "created_at": "2025-01-01T00:00:00Z",
+                "updated_at": "2025-01-01T00:00:00Z",
+            }],
+            "metadata": { "source": "run-7", "weights": [0.5, 1] },
+            "tags": ["http", "retry"],
+            "agent_id": "learning-001",
+            "relevance_score": 0.75,
+            "access_count": 3,
+            "created_at": "2025-01-01T00:00:00Z",
+            "updated_at": "2025-01-02T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn memory_round_trips_through_bytes() {
+        let original = memory();
+        let bytes = memory_to_bytes(&original);
+        assert_eq!(bytes[0], MEMORY_FORMAT_VERSION);
+        assert!(bytes.len() < serde_json::to_vec(&original).unwrap().len());
+
+        let decoded = memory_from_bytes(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&original).unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let mut bytes = memory_to_bytes(&memory());
+        bytes[0] = MEMORY_FORMAT_VERSION + 1;
+        let err = memory_from_bytes(&bytes).unwrap_err();
+        assert!(matches!(err, MemoryCodecError::UnsupportedVersion(2)));
+        assert!(err.to_string().contains("version 2"));
+
+        assert!(matches!(
+            memory_from_bytes(&[]),
+            Err(MemoryCodecError::Empty)
+        ));
+        assert!(matches!(
+            memory_from_bytes(&[MEMORY_FORMAT_VERSION, 0xff]),
+            Err(MemoryCodecError::Decode(_))
+        ));
+    }
+}
@@ -40,6 +40,27 @@ pub fn inject_context(carrier: &mut HashMap<String, String>) {
     });
 }
 
+/// The current context's W3C `traceparent` header value, for transports that
+/// carry a single value rather than a carrier map.
+///
+/// `None` when there is no valid span context to propagate or no propagator
+/// is installed.
+pub fn current_traceparent() -> Option<String> {
+    current_context_field("traceparent")
+}
+
+/// The current context's W3C `tracestate` header value; `None` when the
+/// trace state is empty or there is nothing to propagate.
+pub fn current_tracestate() -> Option<String> {
+    current_context_field("tracestate").filter(|state| !state.is_empty())
+}
+
+fn current_context_field(key: &str) -> Option<String> {
+    let mut carrier = HashMap::new();
+    inject_context(&mut carrier);
+    carrier.remove(key)
+}
+
 /// Extract a parent trace context from a `HashMap`.
 ///
 /// Use this when handling an incoming Socket.IO event to continue the trace
@@ -47,3 +68,53 @@ pub fn inject_context(carrier: &mut HashMap<String, String>) {
 pub fn extract_context(carrier: &HashMap<String, String>) -> Context {
     global::get_text_map_propagator(|propagator| propagator.extract(&HashMapExtractor(carrier)))
 }
+
+/// Inject the trace context of a `tracing` span (via its OpenTelemetry layer)
+/// into a `HashMap`.
+///
+/// Unlike [`inject_context`], this follows the `tracing` span tree, so it
+/// works with spans created by `tracing::info_span!` and friends.
+pub fn inject_span_context(span: &tracing::Span, carrier: &mut HashMap<String, String>) {
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let context = span.context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HashMapInjector(carrier));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+
+    #[test]
+    fn current_traceparent_under_active_span() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        assert_eq!(current_traceparent(), None);
+        assert_eq!(current_tracestate(), None);
+
+        let span_context = SpanContext::new(
+            TraceId::from_bytes([0x4b; 16]),
+            SpanId::from_bytes([0x0f; 8]),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::from_key_value([("evo", "king")]).unwrap(),
+        );
+        let _active = Context::current()
+            .with_remote_span_context(span_context)
+            .attach();
+
+        let traceparent = current_traceparent().unwrap();
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(parts.len(), 4, "{traceparent}");
+        assert_eq!(parts[0], "00");
+        assert_eq!(parts[1], "4b".repeat(16));
+        assert_eq!(parts[2], "0f".repeat(8));
+        assert_eq!(parts[3], "01");
+        assert_eq!(current_tracestate().as_deref(), Some("evo=king"));
+    }
+}